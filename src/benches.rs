@@ -0,0 +1,451 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use scalerize_client::client::SOCKET_PATH_ENV;
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientOptions, ScalerizeClient};
+
+// Lets the benches report allocations alongside timings.
+#[global_allocator]
+static ALLOC: divan::AllocProfiler = divan::AllocProfiler::system();
+
+static SOCKET: OnceLock<PathBuf> = OnceLock::new();
+static MOCK: Mutex<Option<MockServer>> = Mutex::new(None);
+
+// The benches talk to $SCALERIZE_SOCKET when it is set and otherwise to an
+// in-process mock, started the first time a bench needs a socket.
+fn socket_path() -> &'static Path {
+    SOCKET.get_or_init(|| {
+        configured_socket().unwrap_or_else(|| {
+            let server = MockServer::start().expect("Failed to start the mock server");
+            let path = server.path().to_path_buf();
+            *MOCK.lock().unwrap() = Some(server);
+            path
+        })
+    })
+}
+
+fn configured_socket() -> Option<PathBuf> {
+    std::env::var_os(SOCKET_PATH_ENV)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+fn connect() -> ScalerizeClient {
+    ScalerizeClient::connect_to(socket_path()).expect("Failed to connect")
+}
+
+const LARGE_VALUE_SIZE: usize = 64 * 1024 * 1024;
+
+fn large_value_client() -> ScalerizeClient {
+    let options = ClientOptions::new()
+        .max_value_size(LARGE_VALUE_SIZE)
+        .max_response_size(LARGE_VALUE_SIZE);
+    ScalerizeClient::connect_with(socket_path(), options).expect("Failed to connect")
+}
+
+// One put per iteration over a connection opened up front.
+#[divan::bench]
+fn bench_put_reuse_connection(bencher: divan::Bencher) {
+    let mut client = connect();
+    let key = vec![1, 2, 3, 4];
+    let value = b"Hello, Scalerize!";
+    bencher.bench_local(move || {
+        client.put(2, &key, value).expect("Put failed");
+    });
+}
+
+// The same put, paying for a fresh connection every time.
+#[divan::bench]
+fn bench_put_reconnect_per_op(bencher: divan::Bencher) {
+    let key = vec![1, 2, 3, 4];
+    let value = b"Hello, Scalerize!";
+    bencher.bench_local(move || {
+        let mut client = connect();
+        client.put(2, &key, value).expect("Put failed");
+    });
+}
+
+const VALUE_SIZES: [usize; 4] = [16, 1024, 64 * 1024, 1024 * 1024];
+
+#[divan::bench(args = VALUE_SIZES)]
+fn bench_put_value_size(bencher: divan::Bencher, size: usize) {
+    let value = vec![7u8; size];
+    let mut client = connect();
+    bencher
+        .counter(divan::counter::BytesCount::new(size))
+        .bench_local(move || {
+            client.put(2, b"sized", &value).expect("Put failed");
+        });
+}
+
+#[divan::bench(args = VALUE_SIZES)]
+fn bench_get_value_size(bencher: divan::Bencher, size: usize) {
+    let mut client = connect();
+    let key = format!("sized-{}", size);
+    client.put(2, key.as_bytes(), &vec![7u8; size]).expect("Setup put failed");
+    bencher
+        .counter(divan::counter::BytesCount::new(size))
+        .bench_local(move || {
+            client.get(2, key.as_bytes()).expect("Get failed");
+        });
+}
+
+#[divan::bench]
+fn bench_put_sequential_1000(bencher: divan::Bencher) {
+    let n: u32 = 1000;
+    let keys: Vec<[u8; 4]> = (0..n).map(|i| i.to_be_bytes()).collect();
+    let value = b"Hello, Scalerize!";
+    let mut client = connect();
+    bencher
+        .counter(divan::counter::ItemsCount::new(n))
+        .bench_local(move || {
+            for key in &keys {
+                client.put(2, key, value).expect("Put failed");
+            }
+        });
+}
+
+#[divan::bench]
+fn bench_put_pipelined_1000(bencher: divan::Bencher) {
+    let n: u32 = 1000;
+    let keys: Vec<[u8; 4]> = (0..n).map(|i| i.to_be_bytes()).collect();
+    let value = b"Hello, Scalerize!";
+    let mut client = connect();
+    bencher
+        .counter(divan::counter::ItemsCount::new(n))
+        .bench_local(move || {
+            let mut pipeline = client.pipeline();
+            for key in &keys {
+                pipeline.put(2, key, value);
+            }
+            for reply in pipeline.flush().expect("Pipeline flush failed") {
+                reply.expect("Put failed");
+            }
+        });
+}
+
+#[divan::bench]
+fn bench_put_many_1000(bencher: divan::Bencher) {
+    let n: u32 = 1000;
+    let keys: Vec<[u8; 4]> = (0..n).map(|i| i.to_be_bytes()).collect();
+    let value = b"Hello, Scalerize!";
+    let mut client = connect();
+    bencher
+        .counter(divan::counter::ItemsCount::new(n))
+        .bench_local(move || {
+            let pairs: Vec<(&[u8], &[u8])> = keys.iter().map(|key| (&key[..], &value[..])).collect();
+            client.put_many(2, &pairs).expect("Put many failed");
+        });
+}
+
+// A multi-get: 1000 gets queued on one pipeline and answered in one flush.
+#[divan::bench]
+fn bench_get_pipelined_1000(bencher: divan::Bencher) {
+    let n: u32 = 1000;
+    let keys: Vec<[u8; 4]> = (0..n).map(|i| i.to_be_bytes()).collect();
+    let mut client = connect();
+    let pairs: Vec<(&[u8], &[u8])> = keys.iter().map(|key| (&key[..], &b"Hello, Scalerize!"[..])).collect();
+    client.put_many(2, &pairs).expect("Setup put failed");
+    bencher
+        .counter(divan::counter::ItemsCount::new(n))
+        .bench_local(move || {
+            let mut pipeline = client.pipeline();
+            for key in &keys {
+                pipeline.get(2, key);
+            }
+            for reply in pipeline.flush().expect("Pipeline flush failed") {
+                reply.expect("Get failed");
+            }
+        });
+}
+
+#[divan::bench(sample_count = 10)]
+fn bench_put_64mb_vec(bencher: divan::Bencher) {
+    let value = vec![7u8; LARGE_VALUE_SIZE];
+    let mut client = large_value_client();
+    bencher
+        .counter(divan::counter::BytesCount::new(LARGE_VALUE_SIZE))
+        .bench_local(move || {
+            client.put(2, b"large", &value).expect("Put failed");
+        });
+}
+
+#[divan::bench(sample_count = 10)]
+fn bench_put_64mb_reader(bencher: divan::Bencher) {
+    let value = vec![7u8; LARGE_VALUE_SIZE];
+    let mut client = large_value_client();
+    bencher
+        .counter(divan::counter::BytesCount::new(LARGE_VALUE_SIZE))
+        .bench_local(move || {
+            client
+                .put_reader(2, b"large", value.len() as u64, &value[..])
+                .expect("Put failed");
+        });
+}
+
+#[divan::bench(sample_count = 10)]
+fn bench_get_64mb_vec(bencher: divan::Bencher) {
+    let mut client = large_value_client();
+    client.put(2, b"large", &vec![7u8; LARGE_VALUE_SIZE]).expect("Setup put failed");
+    bencher
+        .counter(divan::counter::BytesCount::new(LARGE_VALUE_SIZE))
+        .bench_local(move || {
+            client.get(2, b"large").expect("Get failed");
+        });
+}
+
+#[divan::bench(sample_count = 10)]
+fn bench_get_64mb_writer(bencher: divan::Bencher) {
+    let mut client = large_value_client();
+    client.put(2, b"large", &vec![7u8; LARGE_VALUE_SIZE]).expect("Setup put failed");
+    bencher
+        .counter(divan::counter::BytesCount::new(LARGE_VALUE_SIZE))
+        .bench_local(move || {
+            client.get_writer(2, b"large", std::io::sink()).expect("Get failed");
+        });
+}
+
+#[divan::bench]
+fn bench_get_operation(bencher: divan::Bencher) {
+    // Setup initial data
+    let mut client = connect();
+    let store_number = 2u8;
+    let key = vec![1, 2, 3, 4];
+    let value = b"Hello, Scalerize!";
+    client.put(store_number, &key, value).expect("Setup put failed");
+    client.write(store_number).expect("Setup write failed");
+
+    bencher
+        .counter(divan::counter::ItemsCount::new(1u32))
+        .bench_local(move || {
+            client.get(store_number, &key).expect("Get failed");
+        });
+}
+
+#[divan::bench]
+fn bench_write_operation(bencher: divan::Bencher) {
+    bencher
+        .counter(divan::counter::ItemsCount::new(1u32))
+        .bench_local(move || {
+            let mut client = connect();
+            client.write(2).expect("Write failed");
+        });
+}
+
+#[divan::bench]
+fn bench_full_cycle(bencher: divan::Bencher) {
+    bencher
+        .counter(divan::counter::ItemsCount::new(1u32))
+        .bench_local(move || {
+            let mut client = connect();
+            let store_number = 2u8;
+            let key = vec![1, 2, 3, 4];
+            let value = b"Hello, Scalerize!";
+
+            client.put(store_number, &key, value).expect("Put failed");
+            client.write(store_number).expect("Write failed");
+            client.get(store_number, &key).expect("Get failed");
+            client.delete(store_number, &key).expect("Delete failed");
+        });
+}
+
+// Encoding and decoding alone, with no socket involved; the sizes in the
+// argument names are payload bytes or entry counts.
+mod protocol {
+    use divan::{black_box, Bencher};
+    use scalerize_client::protocol::{self, Request, Response, Status, RESPONSE_HEADER_LEN};
+
+    const KEY_SIZES: [usize; 3] = [8, 256, 4096];
+    const VALUE_SIZES: [usize; 4] = [16, 1024, 64 * 1024, 1024 * 1024];
+    const ENTRY_COUNTS: [usize; 3] = [10, 1000, 10_000];
+    const BATCH_SIZES: [usize; 3] = [10, 100, 1000];
+
+    // A SCAN payload of `count` 8-byte keys with 16-byte values.
+    fn scan_payload(count: usize, with_values: bool) -> Vec<u8> {
+        let mut payload = (count as u32).to_be_bytes().to_vec();
+        for i in 0..count {
+            payload.extend_from_slice(&8u32.to_be_bytes());
+            payload.extend_from_slice(&(i as u64).to_be_bytes());
+            if with_values {
+                payload.extend_from_slice(&16u32.to_be_bytes());
+                payload.extend_from_slice(&[7u8; 16]);
+            }
+        }
+        payload
+    }
+
+    fn response(status: Status, payload: &[u8]) -> Vec<u8> {
+        Response { status, payload }.encode().unwrap()
+    }
+
+    #[divan::bench(args = VALUE_SIZES)]
+    fn encode_put_value_bytes(bencher: Bencher, size: usize) {
+        let value = vec![7u8; size];
+        bencher.counter(divan::counter::BytesCount::new(size)).bench_local(|| {
+            Request::Put {
+                store: black_box(2),
+                key: black_box(b"key-0001"),
+                value: black_box(&value),
+            }
+            .encode()
+        });
+    }
+
+    #[divan::bench(args = KEY_SIZES)]
+    fn encode_get_key_bytes(bencher: Bencher, size: usize) {
+        let key = vec![b'k'; size];
+        bencher.bench_local(|| {
+            Request::Get {
+                store: black_box(2),
+                key: black_box(&key),
+            }
+            .encode()
+        });
+    }
+
+    #[divan::bench(args = VALUE_SIZES)]
+    fn encode_framed_put_value_bytes(bencher: Bencher, size: usize) {
+        let value = vec![7u8; size];
+        let mut out = Vec::with_capacity(size + 64);
+        bencher.bench_local(|| {
+            out.clear();
+            Request::Put {
+                store: black_box(2),
+                key: black_box(b"key-0001"),
+                value: black_box(&value),
+            }
+            .encode_framed(&mut out)
+            .unwrap();
+            black_box(out.len())
+        });
+    }
+
+    #[divan::bench(args = VALUE_SIZES)]
+    fn decode_success_value_bytes(bencher: Bencher, size: usize) {
+        // Parsing borrows the payload, so this should not grow with size.
+        let frame = response(Status::Success, &vec![7u8; size]);
+        bencher.bench_local(|| Response::parse(black_box(&frame)).map(|response| response.payload.len()));
+    }
+
+    #[divan::bench]
+    fn decode_error_message(bencher: Bencher) {
+        let frame = response(Status::Error, b"store 9 is not open");
+        bencher.bench_local(|| Response::parse(black_box(&frame)).map(|response| response.payload.len()));
+    }
+
+    #[divan::bench(args = ENTRY_COUNTS)]
+    fn decode_scan_entries(bencher: Bencher, count: usize) {
+        let payload = scan_payload(count, true);
+        bencher
+            .counter(divan::counter::ItemsCount::new(count))
+            .bench_local(|| protocol::decode_entries(black_box(&payload)));
+    }
+
+    #[divan::bench(args = ENTRY_COUNTS)]
+    fn decode_scan_keys(bencher: Bencher, count: usize) {
+        let payload = scan_payload(count, false);
+        bencher
+            .counter(divan::counter::ItemsCount::new(count))
+            .bench_local(|| protocol::decode_keys(black_box(&payload)));
+    }
+
+    // The WireReader bounds checks on a payload whose count promises more
+    // entries than it holds, which must fail without a large allocation.
+    #[divan::bench(args = ENTRY_COUNTS)]
+    fn reject_truncated_scan_entries(bencher: Bencher, count: usize) {
+        let mut payload = scan_payload(count, true);
+        payload[..4].copy_from_slice(&u32::MAX.to_be_bytes());
+        bencher.bench_local(|| protocol::decode_entries(black_box(&payload)).is_err());
+    }
+
+    // The request side of a multi-get: `count` framed GETs in one buffer.
+    #[divan::bench(args = BATCH_SIZES)]
+    fn encode_multi_get_keys(bencher: Bencher, count: usize) {
+        let keys: Vec<[u8; 8]> = (0..count as u64).map(|i| i.to_be_bytes()).collect();
+        let mut out = Vec::with_capacity(count * 16);
+        bencher
+            .counter(divan::counter::ItemsCount::new(count))
+            .bench_local(|| {
+                out.clear();
+                for key in &keys {
+                    Request::Get {
+                        store: black_box(2),
+                        key: black_box(key),
+                    }
+                    .encode_framed(&mut out)
+                    .unwrap();
+                }
+                black_box(out.len())
+            });
+    }
+
+    // The reply side: `count` back-to-back frames, every other one a miss,
+    // split on their headers the way a pipeline flush reads them.
+    #[divan::bench(args = BATCH_SIZES)]
+    fn decode_multi_get_replies(bencher: Bencher, count: usize) {
+        let mut frames = Vec::new();
+        for i in 0..count {
+            match i % 2 {
+                0 => frames.extend(response(Status::Success, &[7u8; 16])),
+                _ => frames.extend(response(Status::NotFound, b"")),
+            }
+        }
+        bencher
+            .counter(divan::counter::ItemsCount::new(count))
+            .bench_local(|| {
+                let mut rest = black_box(&frames[..]);
+                let mut found = 0;
+                while !rest.is_empty() {
+                    let header: &[u8; RESPONSE_HEADER_LEN] = rest[..RESPONSE_HEADER_LEN].try_into().unwrap();
+                    let (frame, tail) = rest.split_at(RESPONSE_HEADER_LEN + protocol::payload_len(header));
+                    if Response::parse(frame).unwrap().status == Status::Success {
+                        found += 1;
+                    }
+                    rest = tail;
+                }
+                found
+            });
+    }
+
+    #[divan::bench(args = VALUE_SIZES)]
+    fn crc32_bytes(bencher: Bencher, size: usize) {
+        let data = vec![7u8; size];
+        bencher
+            .counter(divan::counter::BytesCount::new(size))
+            .bench_local(|| protocol::crc32(black_box(&data)));
+    }
+}
+
+// Building a fully configured ClientOptions, also without a socket.
+mod options {
+    use std::time::Duration;
+
+    use divan::black_box;
+    use scalerize_client::ClientOptions;
+
+    #[divan::bench]
+    fn build_client_options() -> ClientOptions {
+        ClientOptions::new()
+            .read_timeout(black_box(Some(Duration::from_secs(5))))
+            .write_timeout(black_box(Some(Duration::from_secs(5))))
+            .connect_retries(black_box(3), Duration::from_millis(50))
+            .max_response_size(black_box(1024 * 1024))
+            .max_key_size(black_box(1024))
+            .max_value_size(black_box(1024 * 1024))
+            .max_store_number(black_box(16))
+            .reconnect(black_box(3), Duration::from_millis(50))
+            .handshake(black_box(true))
+            .checksums(black_box(true))
+    }
+}
+
+pub fn run() {
+    match configured_socket() {
+        Some(path) => println!("Running benchmarks against {}...", path.display()),
+        None => println!("Running benchmarks; socket benches use an in-process mock server..."),
+    }
+    divan::main();
+    // Statics are never dropped; this removes the mock's socket file.
+    drop(MOCK.lock().unwrap().take());
+}
@@ -0,0 +1,281 @@
+use std::ffi::OsString;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use scalerize_client::{ClientError, ScalerizeClient};
+
+// Exit codes let scripts tell a server-side failure apart from not being able
+// to reach the server at all. 2 is what clap uses for usage errors.
+pub const EXIT_OPERATION_FAILED: u8 = 1;
+pub const EXIT_USAGE: u8 = 2;
+pub const EXIT_NOT_FOUND: u8 = 3;
+pub const EXIT_CONNECTION: u8 = 4;
+pub const EXIT_INVALID_RESPONSE: u8 = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Raw,
+    Hex,
+}
+
+fn store_arg() -> Arg {
+    Arg::new("store")
+        .required(true)
+        .value_parser(value_parser!(u8))
+        .help("Store number")
+}
+
+fn bytes_arg(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name)
+        .required(true)
+        .value_parser(value_parser!(OsString))
+        .help(help)
+}
+
+pub fn command() -> Command {
+    Command::new("scalerize")
+        .about("Talk to a scalerize server over its Unix socket")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("socket")
+                .long("socket")
+                .global(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Socket path [default: $SCALERIZE_SOCKET or /tmp/scalerize]"),
+        )
+        .arg(
+            Arg::new("hex")
+                .long("hex")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("raw")
+                .help("Parse keys/values as hex and print values as hex"),
+        )
+        .arg(
+            Arg::new("raw")
+                .long("raw")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Use keys/values as given and print values unmodified (default)"),
+        )
+        .subcommand(
+            Command::new("put")
+                .about("Store a value")
+                .arg(store_arg())
+                .arg(bytes_arg("key", "Key"))
+                .arg(bytes_arg("value", "Value")),
+        )
+        .subcommand(
+            Command::new("get")
+                .about("Print a value to stdout")
+                .arg(store_arg())
+                .arg(bytes_arg("key", "Key")),
+        )
+        .subcommand(
+            Command::new("delete")
+                .about("Delete a key")
+                .arg(store_arg())
+                .arg(bytes_arg("key", "Key")),
+        )
+        .subcommand(
+            Command::new("write")
+                .about("Flush a store's pending changes to disk")
+                .arg(store_arg()),
+        )
+        .subcommand(Command::new("stores").about("List the store numbers the server has open"))
+}
+
+pub fn run() -> ExitCode {
+    let matches = command().get_matches();
+    let encoding = if matches.get_flag("hex") {
+        Encoding::Hex
+    } else {
+        Encoding::Raw
+    };
+
+    match dispatch(&matches, encoding) {
+        Ok(code) => ExitCode::from(code),
+        Err(CliError::Usage(message)) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(EXIT_USAGE)
+        }
+        Err(CliError::Client(e)) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+enum CliError {
+    Usage(String),
+    Client(ClientError),
+}
+
+impl From<ClientError> for CliError {
+    fn from(e: ClientError) -> Self {
+        CliError::Client(e)
+    }
+}
+
+fn exit_code(e: &ClientError) -> u8 {
+    match e {
+        ClientError::OperationFailed(_) | ClientError::Unsupported(_) => EXIT_OPERATION_FAILED,
+        ClientError::InvalidArgument(_) => EXIT_USAGE,
+        ClientError::InvalidResponse(_) | ClientError::ChecksumMismatch { .. } => EXIT_INVALID_RESPONSE,
+        ClientError::Io(_)
+        | ClientError::Connect { .. }
+        | ClientError::ReconnectFailed { .. }
+        | ClientError::IncompatibleProtocol { .. }
+        | ClientError::Timeout => EXIT_CONNECTION,
+    }
+}
+
+fn dispatch(matches: &ArgMatches, encoding: Encoding) -> Result<u8, CliError> {
+    let (name, sub) = matches.subcommand().expect("subcommand is required");
+    let mut client = match matches.get_one::<PathBuf>("socket") {
+        Some(path) => ScalerizeClient::connect_to(path)?,
+        None => ScalerizeClient::connect()?,
+    };
+
+    match name {
+        "put" => {
+            let store = *sub.get_one::<u8>("store").expect("required");
+            let key = input_bytes(sub, "key", encoding)?;
+            let value = input_bytes(sub, "value", encoding)?;
+            client.put(store, &key, &value)?;
+        }
+        "get" => {
+            let store = *sub.get_one::<u8>("store").expect("required");
+            let key = input_bytes(sub, "key", encoding)?;
+            match client.get(store, &key)? {
+                Some(value) => print_value(&value, encoding),
+                None => {
+                    eprintln!("key not found");
+                    return Ok(EXIT_NOT_FOUND);
+                }
+            }
+        }
+        "delete" => {
+            let store = *sub.get_one::<u8>("store").expect("required");
+            let key = input_bytes(sub, "key", encoding)?;
+            client.delete(store, &key)?;
+        }
+        "write" => {
+            let store = *sub.get_one::<u8>("store").expect("required");
+            client.write(store)?;
+        }
+        "stores" => {
+            for store in client.list_stores()? {
+                println!("{}", store);
+            }
+        }
+        _ => unreachable!("unknown subcommand {}", name),
+    }
+    Ok(0)
+}
+
+fn input_bytes(matches: &ArgMatches, name: &str, encoding: Encoding) -> Result<Vec<u8>, CliError> {
+    let arg = matches.get_one::<OsString>(name).expect("required");
+    match encoding {
+        Encoding::Raw => Ok(arg.as_bytes().to_vec()),
+        Encoding::Hex => decode_hex(arg.as_bytes())
+            .ok_or_else(|| CliError::Usage(format!("{} is not valid hex: {}", name, arg.to_string_lossy()))),
+    }
+}
+
+fn print_value(value: &[u8], encoding: Encoding) {
+    let mut stdout = std::io::stdout().lock();
+    let result = match encoding {
+        Encoding::Raw => stdout.write_all(value),
+        Encoding::Hex => writeln!(stdout, "{}", encode_hex(value)),
+    };
+    // A closed pipe (e.g. `| head`) is not worth a panic.
+    let _ = result.and_then(|_| stdout.flush());
+}
+
+fn decode_hex(input: &[u8]) -> Option<Vec<u8>> {
+    let input = input.strip_prefix(b"0x").unwrap_or(input);
+    if !input.len().is_multiple_of(2) {
+        return None;
+    }
+    input
+        .chunks(2)
+        .map(|pair| {
+            let hi = (pair[0] as char).to_digit(16)?;
+            let lo = (pair[1] as char).to_digit(16)?;
+            Some((hi * 16 + lo) as u8)
+        })
+        .collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn io(kind: std::io::ErrorKind) -> std::io::Error {
+        std::io::Error::new(kind, "test")
+    }
+
+    #[test]
+    fn exit_codes_separate_server_failures_from_connection_failures() {
+        assert_eq!(exit_code(&ClientError::OperationFailed(b"boom".to_vec())), EXIT_OPERATION_FAILED);
+        assert_eq!(exit_code(&ClientError::Unsupported("ping")), EXIT_OPERATION_FAILED);
+        assert_eq!(exit_code(&ClientError::InvalidArgument("key is empty".to_string())), EXIT_USAGE);
+        assert_eq!(exit_code(&ClientError::InvalidResponse("short".to_string())), EXIT_INVALID_RESPONSE);
+        assert_eq!(
+            exit_code(&ClientError::ChecksumMismatch { expected: 1, actual: 2 }),
+            EXIT_INVALID_RESPONSE
+        );
+        assert_eq!(exit_code(&ClientError::Timeout), EXIT_CONNECTION);
+        assert_eq!(exit_code(&ClientError::Io(io(std::io::ErrorKind::BrokenPipe))), EXIT_CONNECTION);
+        assert_eq!(
+            exit_code(&ClientError::Connect {
+                path: PathBuf::from("/tmp/none"),
+                source: io(std::io::ErrorKind::NotFound),
+            }),
+            EXIT_CONNECTION
+        );
+        assert_eq!(
+            exit_code(&ClientError::ReconnectFailed {
+                attempts: 3,
+                last: io(std::io::ErrorKind::ConnectionRefused),
+            }),
+            EXIT_CONNECTION
+        );
+        assert_eq!(
+            exit_code(&ClientError::IncompatibleProtocol { client: 0x0100, server: 0x0200 }),
+            EXIT_CONNECTION
+        );
+    }
+
+    #[test]
+    fn exit_codes_are_distinct() {
+        let codes = [EXIT_OPERATION_FAILED, EXIT_USAGE, EXIT_NOT_FOUND, EXIT_CONNECTION, EXIT_INVALID_RESPONSE];
+        for (i, code) in codes.iter().enumerate() {
+            assert!(*code != 0);
+            assert!(!codes[i + 1..].contains(code));
+        }
+    }
+
+    #[test]
+    fn hex_input_accepts_an_optional_prefix() {
+        assert_eq!(decode_hex(b"0x01ff"), Some(vec![0x01, 0xff]));
+        assert_eq!(decode_hex(b"01FF"), Some(vec![0x01, 0xff]));
+        assert_eq!(decode_hex(b"abc"), None);
+        assert_eq!(decode_hex(b"zz"), None);
+        assert_eq!(encode_hex(&[0x00, 0xab]), "00ab");
+    }
+
+    #[test]
+    fn command_definition_is_valid() {
+        command().debug_assert();
+    }
+}
@@ -0,0 +1,1120 @@
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::display::display_bytes;
+use crate::metrics::OperationMeta;
+use crate::options::ClientOptions;
+use crate::pipeline::Pipeline;
+use crate::store::StoreHandle;
+use crate::protocol::{self, Crc32, Event, ProtocolError, Request, Response, ServerInfo, Status, TxnOp};
+use crate::subscription::Subscription;
+use crate::transaction::Txn;
+
+pub use crate::protocol::{
+    KvPair, CHECKSUM_LEN, OP_COMMIT, OP_DELETE, OP_ENABLE_CHECKSUMS, OP_FRAMED, OP_GET, OP_GOODBYE, OP_LIST_STORES, OP_PING, OP_PUT, OP_PUT_IF, OP_PUT_MANY, OP_PUT_TTL, OP_SCAN,
+    OP_SCAN_KEYS, OP_SERVER_INFO, OP_SUBSCRIBE, OP_WRITE, PROTOCOL_VERSION, RESPONSE_HEADER_LEN, STATUS_CONDITION_FAILED, STATUS_ERROR, STATUS_NOT_FOUND,
+    STATUS_SUCCESS, STATUS_UNSUPPORTED,
+};
+
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 64 * 1024 * 1024;
+pub const DEFAULT_MAX_KEY_SIZE: usize = 64 * 1024;
+pub const DEFAULT_MAX_VALUE_SIZE: usize = 16 * 1024 * 1024;
+/// How long `close` waits for the server to hang up when no read timeout
+/// is set.
+pub const DEFAULT_CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+// Buffer size for put_reader and get_writer.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+pub const SOCKET_PATH: &str = "/tmp/scalerize";
+pub const SOCKET_PATH_ENV: &str = "SCALERIZE_SOCKET";
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to connect to {}: {source}", .path.display())]
+    Connect {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Reconnect failed after {attempts} attempts: {last}")]
+    ReconnectFailed {
+        attempts: u32,
+        last: std::io::Error,
+    },
+    #[error("Timed out waiting on the server socket")]
+    Timeout,
+    /// `expected` is the checksum the server sent, `actual` the one
+    /// computed over the bytes received.
+    #[error("Response checksum mismatch: server sent {expected:#010x}, received bytes hash to {actual:#010x}")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+    #[error("Server speaks protocol {server:#06x}, this client speaks {client:#06x}")]
+    IncompatibleProtocol { client: u16, server: u16 },
+    #[error("Server does not support {0}")]
+    Unsupported(&'static str),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    /// The server's error message, exactly as sent.
+    #[error("Operation failed: {}", display_bytes(.0))]
+    OperationFailed(Vec<u8>),
+    #[error("Invalid response from server: {0}")]
+    InvalidResponse(String),
+}
+
+impl From<ProtocolError> for ClientError {
+    fn from(e: ProtocolError) -> Self {
+        match e {
+            // Only encoding produces this; it is the caller's input that is wrong.
+            ProtocolError::TooLong(_) => ClientError::InvalidArgument(e.to_string()),
+            _ => ClientError::InvalidResponse(e.to_string()),
+        }
+    }
+}
+
+impl ClientError {
+    /// True when the error means the connection itself is gone, so the same
+    /// request can be retried on a freshly dialed socket.
+    pub fn is_disconnect(&self) -> bool {
+        match self {
+            ClientError::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::NotConnected
+                    | std::io::ErrorKind::UnexpectedEof
+            ),
+            _ => false,
+        }
+    }
+
+    // Socket timeouts come back as WouldBlock or TimedOut depending on the
+    // platform; both map to the dedicated variant.
+    fn from_socket(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => ClientError::Timeout,
+            _ => ClientError::Io(e),
+        }
+    }
+
+    fn into_io(self) -> std::io::Error {
+        match self {
+            ClientError::Io(e) | ClientError::Connect { source: e, .. } => e,
+            other => std::io::Error::other(other.to_string()),
+        }
+    }
+}
+
+pub struct ScalerizeClient {
+    stream: UnixStream,
+    path: PathBuf,
+    options: ClientOptions,
+    checksums: bool,
+}
+
+impl ScalerizeClient {
+    /// Connects to the socket named by `SCALERIZE_SOCKET`, or `/tmp/scalerize`
+    /// when the variable is unset.
+    pub fn connect() -> Result<Self, ClientError> {
+        Self::connect_to(default_socket_path())
+    }
+
+    pub fn connect_to(path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        Self::connect_with(path, ClientOptions::default())
+    }
+
+    pub fn connect_with(path: impl AsRef<Path>, options: ClientOptions) -> Result<Self, ClientError> {
+        let path = path.as_ref().to_path_buf();
+        let stream = Self::dial(&path, &options)?;
+        Self::apply_timeouts(&stream, &options)?;
+        let mut client = Self {
+            stream,
+            path,
+            options,
+            checksums: false,
+        };
+        client.prepare_stream()?;
+        Ok(client)
+    }
+
+    // Brings a freshly dialed stream to what the options ask for: the version
+    // handshake, then checksums. Runs again after every reconnect.
+    fn prepare_stream(&mut self) -> Result<(), ClientError> {
+        self.checksums = false;
+        if self.options.handshake {
+            self.handshake()?;
+        }
+        if self.options.checksums {
+            let enabled = self.negotiate_checksums()?;
+            debug_log!("scalerize checksums enabled={}", enabled);
+        }
+        Ok(())
+    }
+
+    fn handshake(&mut self) -> Result<(), ClientError> {
+        let request = Request::ServerInfo;
+        let frame = self.setup_exchange(&request)?;
+        let response = parse_probe_response(&frame, request.name())?;
+        let info = match response.status {
+            Status::Success => ServerInfo::decode(response.payload)?,
+            _ => return Err(unexpected(response)),
+        };
+        if protocol::protocol_major(info.protocol_version) != protocol::protocol_major(PROTOCOL_VERSION) {
+            return Err(ClientError::IncompatibleProtocol {
+                client: PROTOCOL_VERSION,
+                server: info.protocol_version,
+            });
+        }
+        debug_log!("scalerize handshake server_protocol={:#06x} server_version={:?}", info.protocol_version, info.version);
+        Ok(())
+    }
+
+    // Sends an unchecksummed request while a stream is being set up. Unlike
+    // round_trip it never reconnects, so it is safe to use from a reconnect.
+    fn setup_exchange(&mut self, request: &Request<'_>) -> Result<Vec<u8>, ClientError> {
+        let result = self
+            .send_request(&request.encode()?)
+            .and_then(|()| self.read_full_response());
+        if result.is_err() {
+            self.abandon_stream();
+        }
+        result
+    }
+
+    // Sends OP_ENABLE_CHECKSUMS and records whether the server agreed. The
+    // answer is read unchecksummed; on an error the previous mode is kept.
+    fn negotiate_checksums(&mut self) -> Result<bool, ClientError> {
+        let previous = std::mem::replace(&mut self.checksums, false);
+        let result = self.request_checksums();
+        self.checksums = *result.as_ref().unwrap_or(&previous);
+        result
+    }
+
+    fn request_checksums(&mut self) -> Result<bool, ClientError> {
+        let request = Request::EnableChecksums;
+        let frame = self.setup_exchange(&request)?;
+        match parse_probe_response(&frame, request.name()) {
+            Ok(response) => Ok(response.status == Status::Success),
+            Err(ClientError::Unsupported(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Whether requests and responses on this connection carry checksums:
+    /// `ClientOptions::checksums` was set and the server agreed.
+    pub fn checksums_enabled(&self) -> bool {
+        self.checksums
+    }
+
+    // Bytes that follow each frame on the wire besides the frame itself.
+    pub(crate) fn trailer_len(&self) -> usize {
+        if self.checksums {
+            CHECKSUM_LEN
+        } else {
+            0
+        }
+    }
+
+    fn seal(&self, mut request: Vec<u8>) -> Vec<u8> {
+        if self.checksums {
+            protocol::append_checksum(&mut request, 0);
+        }
+        request
+    }
+
+    fn apply_timeouts(stream: &UnixStream, options: &ClientOptions) -> std::io::Result<()> {
+        stream.set_read_timeout(options.read_timeout)?;
+        stream.set_write_timeout(options.write_timeout)
+    }
+
+    fn dial(path: &Path, options: &ClientOptions) -> Result<UnixStream, ClientError> {
+        let mut backoff = options.connect_backoff;
+        let mut attempt = 1;
+        loop {
+            match UnixStream::connect(path) {
+                Ok(stream) => return Ok(stream),
+                Err(source) if attempt >= options.connect_attempts => {
+                    return Err(ClientError::Connect {
+                        path: path.to_path_buf(),
+                        source,
+                    });
+                }
+                Err(e) => {
+                    debug_log!("Connect attempt {} to {} failed: {}", attempt, path.display(), e);
+                    std::thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Caps the payload length accepted from the server. Larger responses are
+    /// rejected with `InvalidResponse` before any payload is buffered; the
+    /// unread payload is left on the stream, so the connection is shut down.
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        self.options.max_response_size = max_response_size;
+    }
+
+    pub fn options(&self) -> &ClientOptions {
+        &self.options
+    }
+
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<(), ClientError> {
+        self.stream.set_read_timeout(timeout)?;
+        self.options.read_timeout = timeout;
+        Ok(())
+    }
+
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> Result<(), ClientError> {
+        self.stream.set_write_timeout(timeout)?;
+        self.options.write_timeout = timeout;
+        Ok(())
+    }
+
+    // Payload contents may be sensitive, so they are only logged with
+    // `trace-log`; `debug-log` alone records sizes and timings.
+    fn log_response(frame: &[u8]) {
+        if !cfg!(feature = "trace-log") {
+            return;
+        }
+
+        debug_log!(
+            "scalerize response status={} payload=\"{}\"",
+            frame[0],
+            display_bytes(&frame[RESPONSE_HEADER_LEN..])
+        );
+    }
+
+    // Only takes the time when the debug log or a metrics sink will use it.
+    pub(crate) fn start_timer(&self) -> Option<Instant> {
+        (cfg!(feature = "debug-log") || self.options.metrics.is_some()).then(Instant::now)
+    }
+
+    pub(crate) fn report(&self, meta: OperationMeta<'_>) {
+        if let Some(sink) = &self.options.metrics {
+            sink.0.on_complete(&meta);
+        }
+    }
+
+    /// Sends a request and reads back the complete response frame, logging
+    /// and reporting one event per call.
+    fn round_trip(&mut self, request: &Request<'_>) -> Result<Vec<u8>, ClientError> {
+        validate(request, &self.options)?;
+        let encoded = self.seal(request.encode()?);
+        let started = self.start_timer();
+        let result = match self.send_request(&encoded).and_then(|()| self.read_full_response()) {
+            Err(e) if e.is_disconnect() && self.options.reconnect_attempts > 0 && request.is_idempotent() => {
+                // Sealed again for the new connection, which may not agree
+                // to checksums.
+                self.reconnect_and_resend(&encoded[..encoded.len() - self.trailer_len()], e)
+            }
+            result => result,
+        };
+        if result.is_err() {
+            self.abandon_stream();
+        }
+        let elapsed = started.map(|t| t.elapsed()).unwrap_or_default();
+        self.report(OperationMeta {
+            op: request.name(),
+            status: result.as_ref().ok().map(|frame| frame[0]),
+            request_len: encoded.len(),
+            response_len: result.as_ref().map_or(0, |frame| frame.len() + self.trailer_len()),
+            elapsed,
+            error: result.as_ref().err(),
+        });
+
+        match &result {
+            Ok(frame) => debug_log!(
+                "scalerize op={} store={} key_len={} value_len={} request_len={} response_len={} status={} elapsed={:?}",
+                request.name(),
+                request.store(),
+                request.key_len(),
+                request.value_len(),
+                encoded.len(),
+                frame.len() - RESPONSE_HEADER_LEN,
+                frame[0],
+                elapsed
+            ),
+            Err(e) => debug_log!(
+                "scalerize op={} store={} key_len={} value_len={} request_len={} error=\"{}\" elapsed={:?}",
+                request.name(),
+                request.store(),
+                request.key_len(),
+                request.value_len(),
+                encoded.len(),
+                e,
+                elapsed
+            ),
+        }
+        result
+    }
+
+    // `request` is the encoded request without its checksum trailer.
+    fn reconnect_and_resend(&mut self, request: &[u8], first: ClientError) -> Result<Vec<u8>, ClientError> {
+        let attempts = self.options.reconnect_attempts;
+        let mut backoff = self.options.reconnect_backoff;
+        let mut last = first;
+
+        for attempt in 1..=attempts {
+            debug_log!("scalerize reconnect attempt={} path={} after=\"{}\"", attempt, self.path.display(), last);
+            std::thread::sleep(backoff);
+            backoff = backoff.saturating_mul(2);
+
+            let dialed = UnixStream::connect(&self.path)
+                .and_then(|stream| Self::apply_timeouts(&stream, &self.options).map(|()| stream));
+            match dialed {
+                Ok(stream) => self.stream = stream,
+                Err(e) => {
+                    last = ClientError::Io(e);
+                    continue;
+                }
+            }
+            match self.prepare_stream() {
+                Ok(()) => {}
+                Err(e) if e.is_disconnect() => {
+                    last = e;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+
+            let sealed = self.seal(request.to_vec());
+            match self.send_request(&sealed).and_then(|()| self.read_full_response()) {
+                Err(e) if e.is_disconnect() => last = e,
+                result => return result,
+            }
+        }
+
+        Err(ClientError::ReconnectFailed {
+            attempts,
+            last: last.into_io(),
+        })
+    }
+
+    pub(crate) fn send_request(&mut self, request: &[u8]) -> Result<(), ClientError> {
+        self.stream.write_all(request).map_err(ClientError::from_socket)?;
+        self.stream.flush().map_err(ClientError::from_socket)
+    }
+
+    pub(crate) fn read_full_response(&mut self) -> Result<Vec<u8>, ClientError> {
+        let header = self.read_response_header()?;
+        self.read_response_payload(header)
+    }
+
+    fn read_response_header(&mut self) -> Result<[u8; RESPONSE_HEADER_LEN], ClientError> {
+        let mut header = [0u8; RESPONSE_HEADER_LEN];
+        if self.stream.read(&mut header[..1]).map_err(ClientError::from_socket)? == 0 {
+            return Err(ClientError::Io(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "server closed the connection before responding",
+            )));
+        }
+        self.stream.read_exact(&mut header[1..]).map_err(ClientError::from_socket)?;
+        Ok(header)
+    }
+
+    fn read_response_payload(&mut self, header: [u8; RESPONSE_HEADER_LEN]) -> Result<Vec<u8>, ClientError> {
+        let payload_len = protocol::payload_len(&header);
+        let max_response_size = self.options.max_response_size;
+        if payload_len > max_response_size {
+            return Err(ClientError::InvalidResponse(format!(
+                "Response payload of {} bytes exceeds the {} byte limit",
+                payload_len, max_response_size
+            )));
+        }
+
+        let mut frame = vec![0u8; RESPONSE_HEADER_LEN + payload_len];
+        frame[..RESPONSE_HEADER_LEN].copy_from_slice(&header);
+        self.stream
+            .read_exact(&mut frame[RESPONSE_HEADER_LEN..])
+            .map_err(ClientError::from_socket)?;
+        if self.checksums {
+            self.verify_checksum(protocol::crc32(&frame))?;
+        }
+
+        Self::log_response(&frame);
+        Ok(frame)
+    }
+
+    // Reads the checksum that follows a response and compares it with the
+    // one computed over the response.
+    fn verify_checksum(&mut self, actual: u32) -> Result<(), ClientError> {
+        let mut trailer = [0u8; CHECKSUM_LEN];
+        self.stream.read_exact(&mut trailer).map_err(ClientError::from_socket)?;
+        let expected = u32::from_be_bytes(trailer);
+        if expected != actual {
+            return Err(ClientError::ChecksumMismatch { expected, actual });
+        }
+        Ok(())
+    }
+
+    // True when nothing is waiting to be read and the server has not hung up,
+    // i.e. the stream sits between exchanges and is safe to hand to someone
+    // else. Any stray byte found is consumed, so a false answer is final.
+    pub(crate) fn is_idle(&self) -> bool {
+        if self.stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let idle = matches!((&self.stream).read(&mut [0u8; 1]), Err(e) if e.kind() == std::io::ErrorKind::WouldBlock);
+        self.stream.set_nonblocking(false).is_ok() && idle
+    }
+
+    // Used once the request/response sequence on the stream can no longer be
+    // trusted, so later calls fail (or reconnect) instead of reading the
+    // answer to somebody else's request. That covers every failed exchange,
+    // a timeout included: the late answer is still on its way.
+    pub(crate) fn abandon_stream(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+
+    /// Starts a batch of requests that are written together and answered in
+    /// order; see [`Pipeline`].
+    pub fn pipeline(&mut self) -> Pipeline<'_> {
+        Pipeline::new(self)
+    }
+
+    /// Round-trip time of an `OP_PING`. Servers that do not know the opcode
+    /// yield `ClientError::Unsupported`.
+    pub fn ping(&mut self) -> Result<Duration, ClientError> {
+        let started = Instant::now();
+        let frame = self.round_trip(&Request::Ping)?;
+        let elapsed = started.elapsed();
+
+        let response = parse_probe_response(&frame, Request::Ping.name())?;
+        match response.status {
+            Status::Success => Ok(elapsed),
+            _ => Err(unexpected(response)),
+        }
+    }
+
+    /// The server's protocol version and self-reported version string.
+    pub fn server_info(&mut self) -> Result<ServerInfo, ClientError> {
+        let frame = self.round_trip(&Request::ServerInfo)?;
+        let response = parse_probe_response(&frame, Request::ServerInfo.name())?;
+        match response.status {
+            Status::Success => Ok(ServerInfo::decode(response.payload)?),
+            _ => Err(unexpected(response)),
+        }
+    }
+
+    /// Store numbers the server currently has open, in ascending order.
+    /// Servers without the opcode yield `ClientError::Unsupported`.
+    pub fn list_stores(&mut self) -> Result<Vec<u8>, ClientError> {
+        let frame = self.round_trip(&Request::ListStores)?;
+        let response = parse_extension_response(&frame, Request::ListStores.name())?;
+        match response.status {
+            Status::Success => Ok(protocol::decode_stores(response.payload)?),
+            _ => Err(unexpected(response)),
+        }
+    }
+
+    /// Binds `store_number` for a run of operations; see [`StoreHandle`].
+    pub fn store(&mut self, store_number: u8) -> StoreHandle<'_> {
+        StoreHandle::new(self, store_number)
+    }
+
+    /// Starts a transaction on `store_number`; see [`Txn`].
+    pub fn begin(&mut self, store_number: u8) -> Txn<'_> {
+        Txn::new(self, store_number)
+    }
+
+    pub(crate) fn commit(&mut self, store_number: u8, ops: &[TxnOp]) -> Result<(), ClientError> {
+        let frame = self.round_trip(&Request::Commit { store: store_number, ops })?;
+        let response = Response::parse(&frame)?;
+        match response.status {
+            Status::Success => Ok(()),
+            Status::Error => Err(ClientError::OperationFailed(commit_failure(response.payload))),
+            Status::Unsupported => Err(ClientError::Unsupported("commit")),
+            _ => Err(unexpected(response)),
+        }
+    }
+
+    /// Opens a second connection to the same socket, with the same options,
+    /// and subscribes it to changes in `store_number`. Events arrive only on
+    /// the returned [`Subscription`], so they never interleave with
+    /// responses on this connection.
+    pub fn subscribe(&self, store_number: u8) -> Result<Subscription, ClientError> {
+        let mut client = Self::connect_with(&self.path, self.options.clone())?;
+        let request = Request::Subscribe { store: store_number };
+        let frame = client.round_trip(&request)?;
+        let response = parse_extension_response(&frame, request.name())?;
+        match response.status {
+            Status::Success => Ok(Subscription::new(client, store_number)),
+            _ => Err(unexpected(response)),
+        }
+    }
+
+    // Reads the next event frame on a subscribed connection; `None` when the
+    // server closed the connection between events. Only a timeout while
+    // waiting for an event to start is reported as `Timeout`: part way
+    // through a frame the stream is out of step and the error is final.
+    pub(crate) fn read_event(&mut self) -> Result<Option<Event>, ClientError> {
+        let mut header = [0u8; protocol::EVENT_HEADER_LEN];
+        if self.stream.read(&mut header[..1]).map_err(ClientError::from_socket)? == 0 {
+            return Ok(None);
+        }
+        let mid_frame = |e: std::io::Error| match e.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => {
+                ClientError::Io(std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out part way through an event"))
+            }
+            _ => ClientError::Io(e),
+        };
+        self.stream.read_exact(&mut header[1..]).map_err(mid_frame)?;
+
+        let key_len = protocol::event_key_len(&header);
+        if key_len > self.options.max_key_size {
+            return Err(ClientError::InvalidResponse(format!(
+                "Event key of {} bytes exceeds the {} byte limit",
+                key_len, self.options.max_key_size
+            )));
+        }
+        let mut frame = vec![0u8; protocol::EVENT_HEADER_LEN + key_len];
+        frame[..protocol::EVENT_HEADER_LEN].copy_from_slice(&header);
+        self.stream
+            .read_exact(&mut frame[protocol::EVENT_HEADER_LEN..])
+            .map_err(mid_frame)?;
+
+        let event = Event::decode(&frame)?;
+        debug_log!("scalerize event kind={:?} store={} key_len={}", event.kind, event.store, event.key.len());
+        Ok(Some(event))
+    }
+
+    /// Returns `Ok(None)` when the server reports the key as missing
+    /// (`STATUS_NOT_FOUND`); `OperationFailed` is reserved for real errors.
+    pub fn get(&mut self, store_number: u8, key: &[u8]) -> Result<Option<Vec<u8>>, ClientError> {
+        let frame = self.round_trip(&Request::Get { store: store_number, key })?;
+        let response = parse_response(&frame)?;
+
+        match response.status {
+            Status::Success => Ok(Some(response.payload.to_vec())),
+            Status::NotFound => Ok(None),
+            _ => Err(unexpected(response)),
+        }
+    }
+
+    pub fn put(&mut self, store_number: u8, key: &[u8], value: &[u8]) -> Result<(), ClientError> {
+        let frame = self.round_trip(&Request::Put {
+            store: store_number,
+            key,
+            value,
+        })?;
+        expect_success(&frame)
+    }
+
+    /// Stores `new_value` only if the key currently holds `expected`, or for
+    /// `expected == None`, only if the key is absent. Returns `Ok(false)` when
+    /// the condition did not hold and nothing was written.
+    ///
+    /// Unlike the other operations this is never resent after a reconnect:
+    /// the first copy may already have been applied.
+    pub fn put_if(
+        &mut self,
+        store_number: u8,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new_value: &[u8],
+    ) -> Result<bool, ClientError> {
+        let frame = self.round_trip(&Request::PutIf {
+            store: store_number,
+            key,
+            expected,
+            value: new_value,
+        })?;
+        let response = parse_response(&frame)?;
+
+        match response.status {
+            Status::Success => Ok(true),
+            Status::ConditionFailed => Ok(false),
+            _ => Err(unexpected(response)),
+        }
+    }
+
+    /// Like `put`, but streams `len` bytes from `reader` through a fixed-size
+    /// buffer instead of building the whole request in memory. The value
+    /// size limit still applies.
+    ///
+    /// If `reader` ends before `len` bytes, the connection is shut down
+    /// part way through the frame, so the server never sees a complete
+    /// request it would misparse, and the call fails with
+    /// `InvalidArgument`. Never resent on reconnect, since the reader has
+    /// already been consumed.
+    pub fn put_reader(&mut self, store_number: u8, key: &[u8], len: u64, mut reader: impl Read) -> Result<(), ClientError> {
+        let request = Request::Put {
+            store: store_number,
+            key,
+            value: &[],
+        };
+        validate(&request, &self.options)?;
+        let max_value_size = u64::try_from(self.options.max_value_size).unwrap_or(u64::MAX);
+        if len > max_value_size {
+            return Err(ClientError::InvalidArgument(format!(
+                "value of {} bytes exceeds the {} byte limit",
+                len, max_value_size
+            )));
+        }
+        let value_len = u32::try_from(len)
+            .map_err(|_| ClientError::InvalidArgument(format!("value of {} bytes does not fit a u32 length", len)))?;
+
+        let header = protocol::encode_put_header(store_number, key, value_len);
+        let started = self.start_timer();
+
+        let frame = self
+            .send_streamed(&header, len, &mut reader)
+            .and_then(|()| self.read_full_response());
+        if frame.is_err() {
+            self.abandon_stream();
+        }
+        let elapsed = started.map(|t| t.elapsed()).unwrap_or_default();
+        self.report(OperationMeta {
+            op: "put_reader",
+            status: frame.as_ref().ok().map(|frame| frame[0]),
+            request_len: header.len() + value_len as usize + self.trailer_len(),
+            response_len: frame.as_ref().map_or(0, |frame| frame.len() + self.trailer_len()),
+            elapsed,
+            error: frame.as_ref().err(),
+        });
+
+        let result = frame.and_then(|frame| expect_success(&frame));
+        match &result {
+            Ok(()) => debug_log!(
+                "scalerize op=put_reader store={} key_len={} value_len={} elapsed={:?}",
+                store_number,
+                key.len(),
+                len,
+                elapsed
+            ),
+            Err(e) => debug_log!(
+                "scalerize op=put_reader store={} key_len={} value_len={} error=\"{}\" elapsed={:?}",
+                store_number,
+                key.len(),
+                len,
+                e,
+                elapsed
+            ),
+        }
+        result
+    }
+
+    fn send_streamed(&mut self, header: &[u8], len: u64, reader: &mut impl Read) -> Result<(), ClientError> {
+        let mut checksum = self.checksums.then(Crc32::new);
+        self.stream.write_all(header).map_err(ClientError::from_socket)?;
+        if let Some(checksum) = &mut checksum {
+            checksum.update(header);
+        }
+
+        let mut buffer = vec![0u8; STREAM_CHUNK_SIZE];
+        let mut sent = 0u64;
+        while sent < len {
+            let want = usize::try_from(len - sent).map_or(buffer.len(), |left| left.min(buffer.len()));
+            let n = match reader.read(&mut buffer[..want]) {
+                Ok(0) => {
+                    return Err(ClientError::InvalidArgument(format!(
+                        "reader ended after {} of {} bytes",
+                        sent, len
+                    )))
+                }
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(ClientError::Io(e)),
+            };
+            self.stream.write_all(&buffer[..n]).map_err(ClientError::from_socket)?;
+            if let Some(checksum) = &mut checksum {
+                checksum.update(&buffer[..n]);
+            }
+            sent += n as u64;
+        }
+        if let Some(checksum) = checksum {
+            self.stream.write_all(&checksum.finish().to_be_bytes()).map_err(ClientError::from_socket)?;
+        }
+        self.stream.flush().map_err(ClientError::from_socket)
+    }
+
+    /// Like `get`, but copies the value into `writer` as it arrives instead
+    /// of buffering it, and returns its length. `max_response_size` does not
+    /// limit a value streamed this way. A failing writer leaves the rest of
+    /// the value unread, so the connection is shut down. With checksums on,
+    /// a mismatch is only detected once `writer` has the whole value.
+    pub fn get_writer(&mut self, store_number: u8, key: &[u8], mut writer: impl Write) -> Result<Option<u64>, ClientError> {
+        let request = Request::Get { store: store_number, key };
+        validate(&request, &self.options)?;
+        let encoded = self.seal(request.encode()?);
+        let started = self.start_timer();
+
+        let exchange = self.send_request(&encoded).and_then(|()| self.receive_get(&mut writer));
+        if exchange.is_err() {
+            self.abandon_stream();
+        }
+        let elapsed = started.map(|t| t.elapsed()).unwrap_or_default();
+        self.report(OperationMeta {
+            op: "get_writer",
+            status: exchange.as_ref().ok().map(|(header, _)| header[0]),
+            request_len: encoded.len(),
+            response_len: exchange
+                .as_ref()
+                .map_or(0, |(header, _)| RESPONSE_HEADER_LEN + protocol::payload_len(header) + self.trailer_len()),
+            elapsed,
+            error: exchange.as_ref().err(),
+        });
+
+        let (header, failure) = exchange?;
+        if let Some(frame) = failure {
+            let response = parse_response(&frame)?;
+            return match response.status {
+                Status::NotFound => Ok(None),
+                _ => Err(unexpected(response)),
+            };
+        }
+
+        let len = protocol::payload_len(&header) as u64;
+        debug_log!(
+            "scalerize op=get_writer store={} key_len={} value_len={} elapsed={:?}",
+            store_number,
+            key.len(),
+            len,
+            elapsed
+        );
+        Ok(Some(len))
+    }
+
+    // Streams a successful value into `writer`; any other response is
+    // buffered and handed back whole for the caller to interpret.
+    fn receive_get(&mut self, writer: &mut impl Write) -> Result<([u8; RESPONSE_HEADER_LEN], Option<Vec<u8>>), ClientError> {
+        let header = self.read_response_header()?;
+        if header[0] != STATUS_SUCCESS {
+            let frame = self.read_response_payload(header)?;
+            return Ok((header, Some(frame)));
+        }
+
+        let len = protocol::payload_len(&header) as u64;
+        let mut checksum = self.checksums.then(Crc32::new);
+        if let Some(checksum) = &mut checksum {
+            checksum.update(&header);
+        }
+        self.receive_streamed(len, writer, checksum.as_mut())?;
+        if let Some(checksum) = checksum {
+            self.verify_checksum(checksum.finish())?;
+        }
+        Ok((header, None))
+    }
+
+    fn receive_streamed(
+        &mut self,
+        len: u64,
+        writer: &mut impl Write,
+        mut checksum: Option<&mut Crc32>,
+    ) -> Result<(), ClientError> {
+        let mut buffer = vec![0u8; STREAM_CHUNK_SIZE];
+        let mut received = 0u64;
+        while received < len {
+            let want = usize::try_from(len - received).map_or(buffer.len(), |left| left.min(buffer.len()));
+            let n = self.stream.read(&mut buffer[..want]).map_err(ClientError::from_socket)?;
+            if n == 0 {
+                return Err(ClientError::Io(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    format!("server closed the connection after {} of {} bytes", received, len),
+                )));
+            }
+            if let Some(checksum) = &mut checksum {
+                checksum.update(&buffer[..n]);
+            }
+            writer.write_all(&buffer[..n])?;
+            received += n as u64;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Stores a value that the server expires after `ttl`, rounded up to
+    /// whole seconds. Once expired, `get` reports the key as missing.
+    ///
+    /// Servers without TTL support yield `ClientError::Unsupported`.
+    pub fn put_with_ttl(&mut self, store_number: u8, key: &[u8], value: &[u8], ttl: Duration) -> Result<(), ClientError> {
+        if ttl.is_zero() {
+            return Err(ClientError::InvalidArgument("ttl must be greater than zero".to_string()));
+        }
+        let ttl_secs = ttl.as_secs().saturating_add(u64::from(ttl.subsec_nanos() > 0));
+
+        let request = Request::PutTtl {
+            store: store_number,
+            key,
+            value,
+            ttl_secs,
+        };
+        let frame = self.round_trip(&request)?;
+        let response = parse_extension_response(&frame, request.name())?;
+
+        match response.status {
+            Status::Success => Ok(()),
+            // Only success and error are defined for this opcode; anything
+            // else comes from a server that does not know it.
+            _ => Err(ClientError::Unsupported(request.name())),
+        }
+    }
+
+    /// Puts every pair in a single request. The server applies the batch as a
+    /// unit and answers with one status, so the first failure fails the call.
+    pub fn put_many(&mut self, store_number: u8, pairs: &[(&[u8], &[u8])]) -> Result<(), ClientError> {
+        if pairs.is_empty() {
+            return Ok(());
+        }
+
+        let frame = self.round_trip(&Request::PutMany {
+            store: store_number,
+            pairs,
+        })?;
+        expect_success(&frame)
+    }
+
+    pub fn delete(&mut self, store_number: u8, key: &[u8]) -> Result<(), ClientError> {
+        let frame = self.round_trip(&Request::Delete { store: store_number, key })?;
+        expect_success(&frame)
+    }
+
+    pub fn write(&mut self, store_number: u8) -> Result<(), ClientError> {
+        let frame = self.round_trip(&Request::Write { store: store_number })?;
+        expect_success(&frame)
+    }
+
+    /// Returns every key/value pair in the store whose key starts with
+    /// `prefix`, or the whole store for `None`.
+    ///
+    /// The result arrives as a single response, so it is bounded by
+    /// `max_response_size`: a bigger one fails with `InvalidResponse` before
+    /// anything is buffered. The server has no paging yet, so there is no
+    /// limit argument or paged iterator either.
+    pub fn scan(&mut self, store_number: u8, prefix: Option<&[u8]>) -> Result<Vec<KvPair>, ClientError> {
+        let frame = self.round_trip(&Request::Scan {
+            store: store_number,
+            prefix: prefix.unwrap_or_default(),
+        })?;
+        let response = parse_response(&frame)?;
+
+        match response.status {
+            Status::Success => Ok(protocol::decode_entries(response.payload)?),
+            _ => Err(unexpected(response)),
+        }
+    }
+
+    /// Like [`scan`](Self::scan), but the server sends only the keys.
+    pub fn scan_keys(&mut self, store_number: u8, prefix: Option<&[u8]>) -> Result<Vec<Vec<u8>>, ClientError> {
+        let frame = self.round_trip(&Request::ScanKeys {
+            store: store_number,
+            prefix: prefix.unwrap_or_default(),
+        })?;
+        let response = parse_response(&frame)?;
+
+        match response.status {
+            Status::Success => Ok(protocol::decode_keys(response.payload)?),
+            _ => Err(unexpected(response)),
+        }
+    }
+
+    /// Discards anything the server sent that no request is waiting for.
+    /// To receive server-initiated events, use [`subscribe`](Self::subscribe).
+    pub fn check_additional_messages(&mut self) {
+        debug_log!("Checking for additional messages...");
+        self.drain_pending(|message| debug_log!("Additional message received: \"{}\"", display_bytes(message)));
+    }
+
+    // Reads whatever the server has already sent without waiting for more,
+    // handing each chunk to `on_message`.
+    fn drain_pending(&mut self, mut on_message: impl FnMut(&[u8])) {
+        // Set socket to non-blocking mode for checking additional messages
+        self.stream.set_nonblocking(true).unwrap_or_else(|e| debug_log!("Failed to set non-blocking mode: {}", e));
+
+        let mut buffer = vec![0u8; 4096];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(n) if n > 0 => on_message(&buffer[..n]),
+                Ok(_) => {
+                    debug_log!("No more messages");
+                    break;
+                }
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                    debug_log!("No more messages");
+                    break;
+                }
+                Err(e) => {
+                    debug_log!("Error reading additional messages: {}", e);
+                    break;
+                }
+            }
+        }
+
+        // Set socket back to blocking mode
+        self.stream.set_nonblocking(false).unwrap_or_else(|e| debug_log!("Failed to set blocking mode: {}", e));
+    }
+
+    /// Says goodbye to the server and closes the connection.
+    ///
+    /// Unread responses are discarded first, then an `OP_GOODBYE` frame is
+    /// sent and the write half is shut down. Whatever the server answers (a
+    /// server that predates the opcode answers with an error) is read and
+    /// dropped until it closes its end, so both sides see a clean EOF rather
+    /// than a reset. That wait is bounded by the read timeout, or by
+    /// [`DEFAULT_CLOSE_TIMEOUT`] when none is set; a server that stays open
+    /// past it yields `Timeout`. A connection that is already gone is not an
+    /// error.
+    pub fn close(mut self) -> Result<(), ClientError> {
+        self.drain_pending(|_| {});
+
+        let result = self
+            .send_request(&self.seal(Request::Goodbye.encode()?))
+            .and_then(|()| self.stream.shutdown(Shutdown::Write).map_err(ClientError::from_socket))
+            .and_then(|()| {
+                let timeout = self.options.read_timeout.unwrap_or(DEFAULT_CLOSE_TIMEOUT);
+                self.stream.set_read_timeout(Some(timeout))?;
+                let mut buffer = [0u8; 4096];
+                while self.stream.read(&mut buffer).map_err(ClientError::from_socket)? > 0 {}
+                Ok(())
+            });
+        debug_log!("scalerize op=close path={} result={:?}", self.path.display(), result);
+
+        match result {
+            Err(e) if e.is_disconnect() => Ok(()),
+            result => result,
+        }
+    }
+}
+
+impl Drop for ScalerizeClient {
+    // Leaving unread responses behind makes the server see ECONNRESET instead
+    // of EOF, so those are discarded before the write half is shut down.
+    fn drop(&mut self) {
+        self.drain_pending(|_| {});
+        let _ = self.stream.shutdown(Shutdown::Write);
+    }
+}
+
+// Rejects what the server would refuse anyway, or what cannot be framed,
+// before any of it reaches the socket.
+pub(crate) fn validate(request: &Request<'_>, options: &ClientOptions) -> Result<(), ClientError> {
+    if request.store() > options.max_store_number {
+        return Err(ClientError::InvalidArgument(format!(
+            "store {} is above the highest store number {}",
+            request.store(),
+            options.max_store_number
+        )));
+    }
+
+    let result = match *request {
+        Request::Put { key, value, .. } | Request::PutTtl { key, value, .. } => {
+            check_key(key, options).and_then(|()| check_size("value", value, options.max_value_size))
+        }
+        Request::Get { key, .. } | Request::Delete { key, .. } => check_key(key, options),
+        Request::PutIf {
+            key, expected, value, ..
+        } => check_key(key, options)
+            .and_then(|()| expected.map_or(Ok(()), |expected| check_size("expected value", expected, options.max_value_size)))
+            .and_then(|()| check_size("value", value, options.max_value_size)),
+        Request::PutMany { pairs, .. } => pairs.iter().enumerate().try_for_each(|(index, (key, value))| {
+            check_key(key, options)
+                .and_then(|()| check_size("value", value, options.max_value_size))
+                .map_err(|message| format!("entry {}: {}", index, message))
+        }),
+        Request::Scan { prefix, .. } | Request::ScanKeys { prefix, .. } => {
+            check_size("prefix", prefix, options.max_key_size)
+        }
+        Request::Commit { ops, .. } => ops.iter().enumerate().try_for_each(|(index, op)| {
+            check_key(op.key(), options)
+                .and_then(|()| op.value().map_or(Ok(()), |value| check_size("value", value, options.max_value_size)))
+                .map_err(|message| format!("op {}: {}", index, message))
+        }),
+        Request::Write { .. }
+        | Request::Goodbye
+        | Request::Ping
+        | Request::ServerInfo
+        | Request::Subscribe { .. }
+        | Request::ListStores
+        | Request::EnableChecksums => Ok(()),
+    };
+    result.map_err(ClientError::InvalidArgument)
+}
+
+fn check_key(key: &[u8], options: &ClientOptions) -> Result<(), String> {
+    if key.is_empty() {
+        return Err("key is empty".to_string());
+    }
+    check_size("key", key, options.max_key_size)
+}
+
+fn check_size(what: &str, bytes: &[u8], max: usize) -> Result<(), String> {
+    if bytes.len() > max {
+        return Err(format!("{} of {} bytes exceeds the {} byte limit", what, bytes.len(), max));
+    }
+    Ok(())
+}
+
+pub fn default_socket_path() -> PathBuf {
+    std::env::var_os(SOCKET_PATH_ENV)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(SOCKET_PATH))
+}
+
+// Server-reported errors are the same for every operation, so they are
+// turned into `OperationFailed` here and callers only match their own statuses.
+pub(crate) fn parse_response(frame: &[u8]) -> Result<Response<'_>, ClientError> {
+    let response = Response::parse(frame)?;
+    if response.status == Status::Error {
+        return Err(ClientError::OperationFailed(response.payload.to_vec()));
+    }
+    Ok(response)
+}
+
+// Like `parse_response`, for opcodes newer than the original four. A server
+// that predates the opcode either says so with STATUS_UNSUPPORTED or answers
+// with a status byte this client does not know; both become `Unsupported`.
+fn parse_extension_response<'a>(frame: &'a [u8], op: &'static str) -> Result<Response<'a>, ClientError> {
+    match Response::parse(frame) {
+        Err(ProtocolError::UnknownStatus(_)) => Err(ClientError::Unsupported(op)),
+        Ok(response) if response.status == Status::Unsupported => Err(ClientError::Unsupported(op)),
+        _ => parse_response(frame),
+    }
+}
+
+// A rejected commit names the operation at fault ahead of the message.
+fn commit_failure(payload: &[u8]) -> Vec<u8> {
+    match payload.split_first_chunk::<4>() {
+        Some((index, message)) => {
+            let mut failure = format!("transaction op {}: ", u32::from_be_bytes(*index)).into_bytes();
+            failure.extend_from_slice(message);
+            failure
+        }
+        None => payload.to_vec(),
+    }
+}
+
+// For PING and SERVER_INFO, which cannot fail on a server that implements
+// them: an error status means the server did not recognise the opcode.
+fn parse_probe_response<'a>(frame: &'a [u8], op: &'static str) -> Result<Response<'a>, ClientError> {
+    match parse_extension_response(frame, op) {
+        Err(ClientError::OperationFailed(_)) => Err(ClientError::Unsupported(op)),
+        result => result,
+    }
+}
+
+pub(crate) fn expect_success(frame: &[u8]) -> Result<(), ClientError> {
+    let response = parse_response(frame)?;
+    match response.status {
+        Status::Success => Ok(()),
+        _ => Err(unexpected(response)),
+    }
+}
+
+pub(crate) fn unexpected(response: Response<'_>) -> ClientError {
+    ClientError::InvalidResponse(format!(
+        "Unexpected status: {}, response: \"{}\"",
+        response.status.as_byte(),
+        display_bytes(response.payload)
+    ))
+}
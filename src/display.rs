@@ -0,0 +1,90 @@
+use std::fmt;
+
+/// How many bytes [`display_bytes`] shows before truncating.
+pub const DEFAULT_DISPLAY_LIMIT: usize = 128;
+
+/// Renders keys, values and server messages for logs and error text.
+///
+/// Printable ASCII is shown as-is, a backslash as `\\`, and every other
+/// byte as `\xNN`. Input longer than the limit is cut off with `...`
+/// followed by its total length, e.g. `abc... (4096 bytes)`.
+pub fn display_bytes(bytes: &[u8]) -> DisplayBytes<'_> {
+    DisplayBytes {
+        bytes,
+        limit: DEFAULT_DISPLAY_LIMIT,
+    }
+}
+
+/// Returned by [`display_bytes`].
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayBytes<'a> {
+    bytes: &'a [u8],
+    limit: usize,
+}
+
+impl DisplayBytes<'_> {
+    /// Shows at most `limit` bytes instead of [`DEFAULT_DISPLAY_LIMIT`].
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl fmt::Display for DisplayBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shown = &self.bytes[..self.bytes.len().min(self.limit)];
+        for &byte in shown {
+            match byte {
+                b'\\' => f.write_str("\\\\")?,
+                0x20..=0x7e => fmt::Write::write_char(f, byte as char)?,
+                _ => write!(f, "\\x{:02x}", byte)?,
+            }
+        }
+        if shown.len() < self.bytes.len() {
+            write!(f, "... ({} bytes)", self.bytes.len())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn printable_ascii_is_shown_as_is() {
+        assert_eq!(display_bytes(b"user:42 ~ok").to_string(), "user:42 ~ok");
+        assert_eq!(display_bytes(b"").to_string(), "");
+    }
+
+    #[test]
+    fn other_bytes_are_escaped() {
+        assert_eq!(display_bytes(&[0x00, 0x1f, 0x7f, 0xff]).to_string(), "\\x00\\x1f\\x7f\\xff");
+        assert_eq!(display_bytes(b"\n\t\"").to_string(), "\\x0a\\x09\"");
+    }
+
+    #[test]
+    fn mixed_input_escapes_only_what_it_must() {
+        assert_eq!(display_bytes(b"key\x00\\v\xc3\xa9").to_string(), "key\\x00\\\\v\\xc3\\xa9");
+    }
+
+    #[test]
+    fn input_at_the_limit_is_shown_whole() {
+        let bytes = [b'a'; DEFAULT_DISPLAY_LIMIT];
+        assert_eq!(display_bytes(&bytes).to_string(), "a".repeat(DEFAULT_DISPLAY_LIMIT));
+        assert_eq!(display_bytes(b"abcd").limit(4).to_string(), "abcd");
+    }
+
+    #[test]
+    fn input_over_the_limit_is_cut_off_with_its_length() {
+        let bytes = [b'a'; DEFAULT_DISPLAY_LIMIT + 1];
+        assert_eq!(
+            display_bytes(&bytes).to_string(),
+            format!("{}... ({} bytes)", "a".repeat(DEFAULT_DISPLAY_LIMIT), DEFAULT_DISPLAY_LIMIT + 1)
+        );
+        assert_eq!(display_bytes(b"abcde").limit(4).to_string(), "abcd... (5 bytes)");
+        // The limit counts input bytes, not escaped output.
+        assert_eq!(display_bytes(&[0xff; 3]).limit(2).to_string(), "\\xff\\xff... (3 bytes)");
+        assert_eq!(display_bytes(b"abc").limit(0).to_string(), "... (3 bytes)");
+    }
+}
@@ -0,0 +1,35 @@
+//! Client for the scalerize key/value server over a Unix domain socket.
+
+// Diagnostic output is compiled in only with the `debug-log` feature; the
+// arguments are still type-checked so nothing goes stale when it is off.
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        if cfg!(feature = "debug-log") {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub mod client;
+pub mod display;
+pub mod metrics;
+pub mod options;
+pub mod pipeline;
+pub mod pool;
+pub mod protocol;
+pub mod store;
+pub mod subscription;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod transaction;
+
+pub use client::{ClientError, KvPair, ScalerizeClient};
+pub use display::{display_bytes, DisplayBytes};
+pub use metrics::{MetricsSink, OperationMeta};
+pub use options::ClientOptions;
+pub use pipeline::{Pipeline, Reply};
+pub use pool::{PooledClient, ScalerizePool};
+pub use protocol::{Event, EventKind, ServerInfo};
+pub use store::StoreHandle;
+pub use subscription::Subscription;
+pub use transaction::Txn;
@@ -0,0 +1,55 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::client::ClientError;
+
+/// What one operation cost on the wire, passed to the [`MetricsSink`] set
+/// with [`ClientOptions::metrics`](crate::ClientOptions::metrics).
+#[derive(Debug, Clone, Copy)]
+pub struct OperationMeta<'a> {
+    /// Operation name as used in the debug log, e.g. `"get"` or `"pipeline"`.
+    pub op: &'static str,
+    /// Raw status byte of the response. `None` when no response arrived,
+    /// and for a pipeline, whose flush carries one status per request.
+    pub status: Option<u8>,
+    /// Bytes written, including the opcode and store bytes.
+    pub request_len: usize,
+    /// Bytes read, including the response header.
+    pub response_len: usize,
+    /// From the first byte written to the last byte read.
+    pub elapsed: Duration,
+    /// Set when the exchange itself failed: an I/O error, a timeout or an
+    /// unreadable response. A server-side failure shows up in `status`.
+    pub error: Option<&'a ClientError>,
+}
+
+/// Receives an [`OperationMeta`] after every operation that reaches the
+/// socket, successful or not. Requests rejected before anything is written
+/// are not reported.
+///
+/// Called on the thread making the request, so keep it cheap; counters and
+/// histograms are the intended use. Closures taking `&OperationMeta`
+/// implement it directly.
+pub trait MetricsSink: Send + Sync {
+    fn on_complete(&self, meta: &OperationMeta<'_>);
+}
+
+impl<F> MetricsSink for F
+where
+    F: Fn(&OperationMeta<'_>) + Send + Sync,
+{
+    fn on_complete(&self, meta: &OperationMeta<'_>) {
+        self(meta)
+    }
+}
+
+// Lets ClientOptions keep deriving Debug and Clone.
+#[derive(Clone)]
+pub(crate) struct SharedSink(pub(crate) Arc<dyn MetricsSink>);
+
+impl fmt::Debug for SharedSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MetricsSink")
+    }
+}
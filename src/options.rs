@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::client::{DEFAULT_MAX_KEY_SIZE, DEFAULT_MAX_RESPONSE_SIZE, DEFAULT_MAX_VALUE_SIZE};
+use crate::metrics::{MetricsSink, SharedSink};
+
+/// Settings applied when a [`ScalerizeClient`](crate::ScalerizeClient) dials
+/// its socket and for the lifetime of the connection.
+///
+/// No timeouts are set by default, which matches a plain blocking
+/// `UnixStream`.
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    pub(crate) read_timeout: Option<Duration>,
+    pub(crate) write_timeout: Option<Duration>,
+    pub(crate) connect_attempts: u32,
+    pub(crate) connect_backoff: Duration,
+    pub(crate) max_response_size: usize,
+    pub(crate) reconnect_attempts: u32,
+    pub(crate) reconnect_backoff: Duration,
+    pub(crate) handshake: bool,
+    pub(crate) max_key_size: usize,
+    pub(crate) max_value_size: usize,
+    pub(crate) max_store_number: u8,
+    pub(crate) metrics: Option<SharedSink>,
+    pub(crate) checksums: bool,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            read_timeout: None,
+            write_timeout: None,
+            connect_attempts: 1,
+            connect_backoff: Duration::ZERO,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            reconnect_attempts: 0,
+            reconnect_backoff: Duration::ZERO,
+            handshake: false,
+            max_key_size: DEFAULT_MAX_KEY_SIZE,
+            max_value_size: DEFAULT_MAX_VALUE_SIZE,
+            max_store_number: u8::MAX,
+            metrics: None,
+            checksums: false,
+        }
+    }
+}
+
+impl ClientOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bounds how long a single read from the socket may block. Expiry
+    /// surfaces as `ClientError::Timeout` and shuts the connection down, as
+    /// the server may still answer; with [`reconnect`](Self::reconnect) set,
+    /// the next idempotent request dials again.
+    pub fn read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    pub fn write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Retries a failed connect up to `retries` more times, sleeping `backoff`
+    /// before the first retry and doubling it before each one after that.
+    pub fn connect_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.connect_attempts = retries.saturating_add(1);
+        self.connect_backoff = backoff;
+        self
+    }
+
+    pub fn max_response_size(mut self, max_response_size: usize) -> Self {
+        self.max_response_size = max_response_size;
+        self
+    }
+
+    /// Longest key (or scan prefix) sent to the server. Longer keys, and
+    /// empty ones, fail with `ClientError::InvalidArgument` before anything
+    /// is written.
+    pub fn max_key_size(mut self, max_key_size: usize) -> Self {
+        self.max_key_size = max_key_size;
+        self
+    }
+
+    /// Longest value sent to the server, checked like `max_key_size`.
+    pub fn max_value_size(mut self, max_value_size: usize) -> Self {
+        self.max_value_size = max_value_size;
+        self
+    }
+
+    /// Highest store number the server accepts. Every store number is
+    /// allowed by default.
+    pub fn max_store_number(mut self, max_store_number: u8) -> Self {
+        self.max_store_number = max_store_number;
+        self
+    }
+
+    /// When the connection drops mid-request (broken pipe, reset, or the
+    /// server closing the socket), re-dial the same path and resend the
+    /// request, up to `max_attempts` times with doubling `backoff`. Disabled
+    /// by default. Only idempotent operations are resent; `put_if` reports
+    /// the disconnect instead, since the first copy may have been applied.
+    pub fn reconnect(mut self, max_attempts: u32, backoff: Duration) -> Self {
+        self.reconnect_attempts = max_attempts;
+        self.reconnect_backoff = backoff;
+        self
+    }
+
+    /// Asks the server for its protocol version right after connecting and
+    /// refuses to continue with `ClientError::IncompatibleProtocol` when the
+    /// major versions differ. A server too old to answer fails the connect
+    /// with `ClientError::Unsupported`. Off by default.
+    pub fn handshake(mut self, handshake: bool) -> Self {
+        self.handshake = handshake;
+        self
+    }
+
+    /// Asks the server to checksum every request and response on the
+    /// connection, so corruption fails with `ClientError::ChecksumMismatch`
+    /// instead of returning bad bytes. A server that does not support it
+    /// leaves checksums off; see `ScalerizeClient::checksums_enabled`. Off by
+    /// default.
+    pub fn checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Reports every operation to `sink`; see [`MetricsSink`]. Clients
+    /// cloned from these options, such as pool connections and
+    /// subscriptions, share the same sink.
+    pub fn metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(SharedSink(sink));
+        self
+    }
+}
@@ -0,0 +1,183 @@
+use crate::client::{expect_success, parse_response, unexpected, validate, ClientError, ScalerizeClient};
+use crate::metrics::OperationMeta;
+use crate::protocol::{self, Request, Status};
+
+/// Successful outcome of one pipelined request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// A put or delete was applied.
+    Done,
+    /// The answer to a get; `None` when the key is missing.
+    Value(Option<Vec<u8>>),
+}
+
+#[derive(Debug)]
+enum Queued {
+    Get,
+    Put,
+    Delete,
+    // Failed validation when queued; nothing was written for it.
+    Rejected(ClientError),
+}
+
+/// Requests queued on one connection and sent with a single write.
+///
+/// Nothing reaches the server until [`flush`](Pipeline::flush). Each request
+/// travels in an `OP_FRAMED` envelope, so this needs a server that knows that
+/// opcode. The server answers in order, so each queued request gets its own
+/// result: one failed request does not affect the ones around it. Requests
+/// that fail validation are not sent and report `InvalidArgument` in their
+/// slot. Pipelined requests are never resent after a dropped connection,
+/// whatever the reconnect options say.
+pub struct Pipeline<'a> {
+    client: &'a mut ScalerizeClient,
+    buffer: Vec<u8>,
+    queued: Vec<Queued>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub(crate) fn new(client: &'a mut ScalerizeClient) -> Self {
+        Pipeline {
+            client,
+            buffer: Vec::new(),
+            queued: Vec::new(),
+        }
+    }
+
+    pub fn get(&mut self, store_number: u8, key: &[u8]) -> &mut Self {
+        self.push(Queued::Get, Request::Get { store: store_number, key })
+    }
+
+    pub fn put(&mut self, store_number: u8, key: &[u8], value: &[u8]) -> &mut Self {
+        self.push(
+            Queued::Put,
+            Request::Put {
+                store: store_number,
+                key,
+                value,
+            },
+        )
+    }
+
+    pub fn delete(&mut self, store_number: u8, key: &[u8]) -> &mut Self {
+        self.push(Queued::Delete, Request::Delete { store: store_number, key })
+    }
+
+    /// Number of requests waiting for the next flush.
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    // A request that fails validation still takes its slot, so the results
+    // returned by flush line up with the queue.
+    fn push(&mut self, kind: Queued, request: Request<'_>) -> &mut Self {
+        let start = self.buffer.len();
+        let queued = validate(&request, self.client.options())
+            .and_then(|()| {
+                request.encode_framed(&mut self.buffer)?;
+                if self.client.checksums_enabled() {
+                    protocol::append_checksum(&mut self.buffer, start);
+                }
+                Ok(())
+            })
+            .map_or_else(Queued::Rejected, |()| kind);
+        self.queued.push(queued);
+        self
+    }
+
+    /// Writes every queued request and reads back one result per request, in
+    /// queue order. The queue is empty afterwards and can be reused.
+    ///
+    /// The outer error means the connection itself failed part way through;
+    /// the remaining answers are lost, so the connection is shut down rather
+    /// than left out of step with its requests.
+    ///
+    /// Answers are only read once every request has been written. A batch
+    /// whose early answers outgrow the socket buffers while later requests
+    /// are still being written stalls until the read or write timeout fires,
+    /// so keep batches of large gets modest.
+    pub fn flush(&mut self) -> Result<Vec<Result<Reply, ClientError>>, ClientError> {
+        let queued = std::mem::take(&mut self.queued);
+        let buffer = std::mem::take(&mut self.buffer);
+        if queued.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let started = self.client.start_timer();
+        let requests = queued.len();
+        let mut received = 0;
+        let result = self.exchange(&buffer, queued, &mut received);
+        let elapsed = started.map(|t| t.elapsed()).unwrap_or_default();
+        self.client.report(OperationMeta {
+            op: "pipeline",
+            status: None,
+            request_len: buffer.len(),
+            response_len: received,
+            elapsed,
+            error: result.as_ref().err(),
+        });
+
+        match &result {
+            Ok(replies) => debug_log!(
+                "scalerize op=pipeline requests={} request_len={} failed={} elapsed={:?}",
+                requests,
+                buffer.len(),
+                replies.iter().filter(|reply| reply.is_err()).count(),
+                elapsed
+            ),
+            Err(e) => debug_log!(
+                "scalerize op=pipeline requests={} request_len={} error=\"{}\" elapsed={:?}",
+                requests,
+                buffer.len(),
+                e,
+                elapsed
+            ),
+        }
+
+        if result.is_err() {
+            self.client.abandon_stream();
+        }
+        result
+    }
+
+    fn exchange(
+        &mut self,
+        buffer: &[u8],
+        queued: Vec<Queued>,
+        received: &mut usize,
+    ) -> Result<Vec<Result<Reply, ClientError>>, ClientError> {
+        if !buffer.is_empty() {
+            self.client.send_request(buffer)?;
+        }
+
+        let mut replies = Vec::with_capacity(queued.len());
+        for kind in queued {
+            let reply = match kind {
+                Queued::Rejected(e) => Err(e),
+                Queued::Get => parse_get(&self.read_counted(received)?),
+                Queued::Put | Queued::Delete => expect_success(&self.read_counted(received)?).map(|()| Reply::Done),
+            };
+            replies.push(reply);
+        }
+        Ok(replies)
+    }
+
+    fn read_counted(&mut self, received: &mut usize) -> Result<Vec<u8>, ClientError> {
+        let frame = self.client.read_full_response()?;
+        *received += frame.len() + self.client.trailer_len();
+        Ok(frame)
+    }
+}
+
+fn parse_get(frame: &[u8]) -> Result<Reply, ClientError> {
+    let response = parse_response(frame)?;
+    match response.status {
+        Status::Success => Ok(Reply::Value(Some(response.payload.to_vec()))),
+        Status::NotFound => Ok(Reply::Value(None)),
+        _ => Err(unexpected(response)),
+    }
+}
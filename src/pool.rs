@@ -0,0 +1,265 @@
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::client::{default_socket_path, ClientError, KvPair, ScalerizeClient};
+use crate::options::ClientOptions;
+
+pub const DEFAULT_MAX_RETRIES: u32 = 1;
+
+struct PoolState {
+    idle: Vec<ScalerizeClient>,
+    open: usize,
+}
+
+/// A fixed-size set of connections that can be shared across threads.
+///
+/// Connections are dialed lazily on first checkout. Once `size` connections
+/// are open, `get()` blocks until a guard is dropped.
+pub struct ScalerizePool {
+    path: PathBuf,
+    size: usize,
+    max_retries: u32,
+    options: ClientOptions,
+    state: Mutex<PoolState>,
+    returned: Condvar,
+}
+
+impl ScalerizePool {
+    pub fn new(size: usize) -> Self {
+        Self::for_path(default_socket_path(), size)
+    }
+
+    pub fn for_path(path: impl AsRef<Path>, size: usize) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            size: size.max(1),
+            max_retries: DEFAULT_MAX_RETRIES,
+            options: ClientOptions::default(),
+            state: Mutex::new(PoolState {
+                idle: Vec::with_capacity(size),
+                open: 0,
+            }),
+            returned: Condvar::new(),
+        }
+    }
+
+    /// How many times an operation is re-dialed and retried after the
+    /// connection turns out to be dead.
+    pub fn set_max_retries(&mut self, max_retries: u32) {
+        self.max_retries = max_retries;
+    }
+
+    /// Options used for every connection the pool dials from now on.
+    pub fn set_client_options(&mut self, options: ClientOptions) {
+        self.options = options;
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self) -> Result<PooledClient<'_>, ClientError> {
+        let mut state = self.lock();
+        loop {
+            if let Some(client) = state.idle.pop() {
+                return Ok(PooledClient::new(self, client));
+            }
+            if state.open < self.size {
+                state.open += 1;
+                drop(state);
+                return match self.dial() {
+                    Ok(client) => Ok(PooledClient::new(self, client)),
+                    Err(e) => {
+                        self.release_slot();
+                        Err(e)
+                    }
+                };
+            }
+            state = self.returned.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    fn dial(&self) -> Result<ScalerizeClient, ClientError> {
+        ScalerizeClient::connect_with(&self.path, self.options.clone())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        // A panic while holding the lock cannot leave PoolState inconsistent,
+        // so a poisoned mutex is still safe to use.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn release_slot(&self) {
+        self.lock().open -= 1;
+        self.returned.notify_one();
+    }
+
+    // A guard that handed out `&mut ScalerizeClient` may have changed the
+    // per-connection settings or left a failed exchange on the stream.
+    fn restore(&self, client: &mut ScalerizeClient) -> bool {
+        client.set_max_response_size(self.options.max_response_size);
+        client.is_idle()
+            && client.set_read_timeout(self.options.read_timeout).is_ok()
+            && client.set_write_timeout(self.options.write_timeout).is_ok()
+    }
+
+    fn checkin(&self, client: ScalerizeClient) {
+        self.lock().idle.push(client);
+        self.returned.notify_one();
+    }
+}
+
+/// A connection checked out of a [`ScalerizePool`], returned to it on drop.
+///
+/// The operation methods re-dial and retry when the connection has died;
+/// anything else on `ScalerizeClient` is reachable through `Deref`. Calls
+/// made that way are not retried. Once the guard has been borrowed mutably,
+/// the connection only goes back to the pool if nothing is left unread on it,
+/// and with the pool's timeouts and response size limit put back.
+pub struct PooledClient<'a> {
+    pool: &'a ScalerizePool,
+    client: Option<ScalerizeClient>,
+    broken: bool,
+    exposed: bool,
+}
+
+impl<'a> PooledClient<'a> {
+    fn new(pool: &'a ScalerizePool, client: ScalerizeClient) -> Self {
+        Self {
+            pool,
+            client: Some(client),
+            broken: false,
+            exposed: false,
+        }
+    }
+
+    pub fn get(&mut self, store_number: u8, key: &[u8]) -> Result<Option<Vec<u8>>, ClientError> {
+        self.with_retry(|client| client.get(store_number, key))
+    }
+
+    pub fn put(&mut self, store_number: u8, key: &[u8], value: &[u8]) -> Result<(), ClientError> {
+        self.with_retry(|client| client.put(store_number, key, value))
+    }
+
+    pub fn ping(&mut self) -> Result<Duration, ClientError> {
+        self.with_retry(|client| client.ping())
+    }
+
+    pub fn put_with_ttl(&mut self, store_number: u8, key: &[u8], value: &[u8], ttl: Duration) -> Result<(), ClientError> {
+        self.with_retry(|client| client.put_with_ttl(store_number, key, value, ttl))
+    }
+
+    pub fn put_many(&mut self, store_number: u8, pairs: &[(&[u8], &[u8])]) -> Result<(), ClientError> {
+        self.with_retry(|client| client.put_many(store_number, pairs))
+    }
+
+    pub fn delete(&mut self, store_number: u8, key: &[u8]) -> Result<(), ClientError> {
+        self.with_retry(|client| client.delete(store_number, key))
+    }
+
+    pub fn write(&mut self, store_number: u8) -> Result<(), ClientError> {
+        self.with_retry(|client| client.write(store_number))
+    }
+
+    pub fn scan(&mut self, store_number: u8, prefix: Option<&[u8]>) -> Result<Vec<KvPair>, ClientError> {
+        self.with_retry(|client| client.scan(store_number, prefix))
+    }
+
+    pub fn scan_keys(&mut self, store_number: u8, prefix: Option<&[u8]>) -> Result<Vec<Vec<u8>>, ClientError> {
+        self.with_retry(|client| client.scan_keys(store_number, prefix))
+    }
+
+    pub fn list_stores(&mut self) -> Result<Vec<u8>, ClientError> {
+        self.with_retry(|client| client.list_stores())
+    }
+
+    /// Never retried, since the first attempt may have been applied before
+    /// the connection died; see [`ScalerizeClient::put_if`].
+    pub fn put_if(
+        &mut self,
+        store_number: u8,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new_value: &[u8],
+    ) -> Result<bool, ClientError> {
+        let result = self.client_mut().put_if(store_number, key, expected, new_value);
+        if let Err(err) = &result {
+            self.note_failure(err);
+        }
+        result
+    }
+
+    fn with_retry<T>(
+        &mut self,
+        mut op: impl FnMut(&mut ScalerizeClient) -> Result<T, ClientError>,
+    ) -> Result<T, ClientError> {
+        let mut attempts = 0;
+        loop {
+            let result = op(self.client_mut());
+            let err = match result {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+
+            self.note_failure(&err);
+            if !err.is_disconnect() || attempts >= self.pool.max_retries {
+                return Err(err);
+            }
+
+            attempts += 1;
+            *self.client_mut() = self.pool.dial()?;
+            self.broken = false;
+        }
+    }
+
+    // Any IO or framing failure may leave the stream mid-response, so the
+    // connection must not go back to the pool as-is.
+    fn note_failure(&mut self, err: &ClientError) {
+        if !matches!(
+            err,
+            ClientError::OperationFailed(_) | ClientError::InvalidArgument(_) | ClientError::Unsupported(_)
+        ) {
+            self.broken = true;
+        }
+    }
+
+    fn client_mut(&mut self) -> &mut ScalerizeClient {
+        self.client.as_mut().expect("pooled client is only taken on drop")
+    }
+}
+
+impl Deref for PooledClient<'_> {
+    type Target = ScalerizeClient;
+
+    fn deref(&self) -> &ScalerizeClient {
+        self.client.as_ref().expect("pooled client is only taken on drop")
+    }
+}
+
+impl DerefMut for PooledClient<'_> {
+    fn deref_mut(&mut self) -> &mut ScalerizeClient {
+        self.exposed = true;
+        self.client_mut()
+    }
+}
+
+impl Drop for PooledClient<'_> {
+    fn drop(&mut self) {
+        match self.client.take() {
+            Some(mut client) if !self.broken => {
+                if !self.exposed || self.pool.restore(&mut client) {
+                    self.pool.checkin(client)
+                } else {
+                    self.pool.release_slot()
+                }
+            }
+            _ => self.pool.release_slot(),
+        }
+    }
+}
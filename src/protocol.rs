@@ -0,0 +1,944 @@
+//! Wire format shared by every client implementation.
+//!
+//! Requests start with an opcode byte and a store byte. The legacy point
+//! operations (PUT, GET, DELETE) send the key unprefixed, so the server reads
+//! it up to the end of the frame (GET/DELETE) or up to the value length (PUT).
+//! Every opcode added since uses u32 big-endian length prefixes, and
+//! pipelined requests are wrapped in an `OP_FRAMED` envelope.
+//!
+//! Responses are `[status][u32 payload_len][payload]`.
+//!
+//! A connection may switch on checksums with `OP_ENABLE_CHECKSUMS`, after
+//! which every request and response frame is followed by the [`crc32`] of
+//! its bytes.
+
+use thiserror::Error;
+
+pub const OP_PUT: u8 = 1;
+pub const OP_GET: u8 = 2;
+pub const OP_DELETE: u8 = 3;
+pub const OP_WRITE: u8 = 4;
+// [op][store][u32 count] then per entry [u32 key_len][key][u32 value_len][value].
+pub const OP_PUT_MANY: u8 = 5;
+// [op][store][u32 prefix_len][prefix]; an empty prefix matches every key.
+// SCAN answers [u32 count] then per entry [u32 key_len][key][u32 value_len][value],
+// SCAN_KEYS answers [u32 count] then per entry [u32 key_len][key].
+pub const OP_SCAN: u8 = 6;
+pub const OP_SCAN_KEYS: u8 = 7;
+// Compare-and-swap:
+// [op][store][u32 key_len][key][u8 has_expected]([u32 expected_len][expected])[u32 value_len][value].
+// has_expected is 0 for insert-if-absent (no expected field follows) or 1.
+// The server answers STATUS_SUCCESS when it stored the value and
+// STATUS_CONDITION_FAILED when the current value did not match.
+pub const OP_PUT_IF: u8 = 8;
+// [op][store][u32 key_len][key][u32 value_len][value][u64 ttl_secs].
+pub const OP_PUT_TTL: u8 = 9;
+// [op][store][u32 len][request]: wraps one complete request of `len` bytes so
+// that several can be written back to back. Unprefixed legacy requests
+// otherwise end wherever the server's read happens to end.
+pub const OP_FRAMED: u8 = 10;
+// [op][store]: the client is about to close the connection. The store byte is
+// always 0. The server may answer, and then closes its end once it reads EOF.
+pub const OP_GOODBYE: u8 = 11;
+// [op][store], store always 0. PING answers STATUS_SUCCESS with an empty
+// payload: 0c 00 -> 01 00 00 00 00.
+pub const OP_PING: u8 = 12;
+// [op][store], store always 0. Answers [u16 protocol_version][u32 version_len][version],
+// e.g. a server on protocol 1.0 calling itself "scalerize 0.4.1":
+// 0d 00 -> 01 00 00 00 15 01 00 00 00 00 0f "scalerize 0.4.1".
+pub const OP_SERVER_INFO: u8 = 13;
+// [op][store]: turns the connection into a subscription to the store's
+// changes. After the STATUS_SUCCESS answer the server only sends event frames
+// on it, and ends the subscription by closing the connection.
+pub const OP_SUBSCRIBE: u8 = 14;
+// [op][store][u32 count] then per operation [op][u32 key_len][key], followed by
+// [u32 value_len][value] when op is OP_PUT (the other allowed op is OP_DELETE).
+// The server applies all operations or none. On failure it answers
+// STATUS_ERROR with [u32 index][message], index being the operation at fault.
+// A put of "a" = "1" and a delete of "b" in store 2:
+// 0f 02 00 00 00 02  01 00 00 00 01 61 00 00 00 01 31  03 00 00 00 01 62.
+pub const OP_COMMIT: u8 = 15;
+// [op][store], store always 0. Answers [u32 count] then one byte per store the
+// server has open, ascending; stores 0 and 3 open:
+// 10 00 -> 01 00 00 00 06 00 00 00 02 00 03.
+pub const OP_LIST_STORES: u8 = 16;
+// [op][store], store always 0. A server that answers STATUS_SUCCESS expects
+// every later request on the connection to be followed by a u32 CRC-32 of
+// its bytes, an OP_FRAMED envelope counting as one request, and follows
+// every later response with a u32 CRC-32 of [status][u32 payload_len][payload].
+// The answer to this request and event frames carry none. PING with
+// checksums on: 0c 00 ed 6c 5d f3 -> 01 00 00 00 00 fb 42 de ad.
+// Any other answer means the server does not checksum.
+pub const OP_ENABLE_CHECKSUMS: u8 = 17;
+
+// Event frames are [event_type][store][u32 key_len][key].
+pub const EVENT_PUT: u8 = 1;
+pub const EVENT_DELETE: u8 = 2;
+// The store was written to disk; the key is empty.
+pub const EVENT_WRITE: u8 = 3;
+
+pub const STATUS_SUCCESS: u8 = 1;
+pub const STATUS_ERROR: u8 = 0;
+pub const STATUS_NOT_FOUND: u8 = 2;
+pub const STATUS_CONDITION_FAILED: u8 = 3;
+// Sent by servers that recognise the frame but do not implement the opcode.
+pub const STATUS_UNSUPPORTED: u8 = 4;
+
+pub const RESPONSE_HEADER_LEN: usize = 5;
+pub const EVENT_HEADER_LEN: usize = 6;
+pub const CHECKSUM_LEN: usize = 4;
+
+// Major version in the high byte, minor in the low byte. Peers with the same
+// major version understand each other.
+pub const PROTOCOL_VERSION: u16 = 0x0100;
+
+pub fn protocol_major(version: u16) -> u8 {
+    (version >> 8) as u8
+}
+
+pub type KvPair = (Vec<u8>, Vec<u8>);
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    #[error("Truncated frame: needed {needed} bytes at offset {offset}, have {available}")]
+    Truncated {
+        offset: usize,
+        needed: usize,
+        available: usize,
+    },
+    #[error("Unknown status byte: {0}")]
+    UnknownStatus(u8),
+    #[error("{0} trailing bytes after the end of the frame")]
+    TrailingBytes(usize),
+    #[error("Unknown event type: {0}")]
+    UnknownEvent(u8),
+    #[error("{0} byte field does not fit a u32 length")]
+    TooLong(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Error,
+    NotFound,
+    ConditionFailed,
+    Unsupported,
+}
+
+impl Status {
+    pub fn from_byte(byte: u8) -> Result<Self, ProtocolError> {
+        match byte {
+            STATUS_SUCCESS => Ok(Status::Success),
+            STATUS_ERROR => Ok(Status::Error),
+            STATUS_NOT_FOUND => Ok(Status::NotFound),
+            STATUS_CONDITION_FAILED => Ok(Status::ConditionFailed),
+            STATUS_UNSUPPORTED => Ok(Status::Unsupported),
+            other => Err(ProtocolError::UnknownStatus(other)),
+        }
+    }
+
+    pub fn as_byte(self) -> u8 {
+        match self {
+            Status::Success => STATUS_SUCCESS,
+            Status::Error => STATUS_ERROR,
+            Status::NotFound => STATUS_NOT_FOUND,
+            Status::ConditionFailed => STATUS_CONDITION_FAILED,
+            Status::Unsupported => STATUS_UNSUPPORTED,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request<'a> {
+    Put { store: u8, key: &'a [u8], value: &'a [u8] },
+    Get { store: u8, key: &'a [u8] },
+    Delete { store: u8, key: &'a [u8] },
+    Write { store: u8 },
+    PutMany { store: u8, pairs: &'a [(&'a [u8], &'a [u8])] },
+    Scan { store: u8, prefix: &'a [u8] },
+    ScanKeys { store: u8, prefix: &'a [u8] },
+    PutIf {
+        store: u8,
+        key: &'a [u8],
+        expected: Option<&'a [u8]>,
+        value: &'a [u8],
+    },
+    PutTtl {
+        store: u8,
+        key: &'a [u8],
+        value: &'a [u8],
+        ttl_secs: u64,
+    },
+    Goodbye,
+    Ping,
+    ServerInfo,
+    Subscribe { store: u8 },
+    Commit { store: u8, ops: &'a [TxnOp] },
+    ListStores,
+    EnableChecksums,
+}
+
+/// One buffered operation of a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxnOp {
+    Put { key: Vec<u8>, value: Vec<u8> },
+    Delete { key: Vec<u8> },
+}
+
+impl TxnOp {
+    pub fn key(&self) -> &[u8] {
+        match self {
+            TxnOp::Put { key, .. } | TxnOp::Delete { key } => key,
+        }
+    }
+
+    pub fn value(&self) -> Option<&[u8]> {
+        match self {
+            TxnOp::Put { value, .. } => Some(value),
+            TxnOp::Delete { .. } => None,
+        }
+    }
+}
+
+impl Request<'_> {
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Request::Put { .. } => OP_PUT,
+            Request::Get { .. } => OP_GET,
+            Request::Delete { .. } => OP_DELETE,
+            Request::Write { .. } => OP_WRITE,
+            Request::PutMany { .. } => OP_PUT_MANY,
+            Request::Scan { .. } => OP_SCAN,
+            Request::ScanKeys { .. } => OP_SCAN_KEYS,
+            Request::PutIf { .. } => OP_PUT_IF,
+            Request::PutTtl { .. } => OP_PUT_TTL,
+            Request::Goodbye => OP_GOODBYE,
+            Request::Ping => OP_PING,
+            Request::ServerInfo => OP_SERVER_INFO,
+            Request::Subscribe { .. } => OP_SUBSCRIBE,
+            Request::Commit { .. } => OP_COMMIT,
+            Request::ListStores => OP_LIST_STORES,
+            Request::EnableChecksums => OP_ENABLE_CHECKSUMS,
+        }
+    }
+
+    pub fn store(&self) -> u8 {
+        match *self {
+            Request::Put { store, .. }
+            | Request::Get { store, .. }
+            | Request::Delete { store, .. }
+            | Request::Write { store }
+            | Request::PutMany { store, .. }
+            | Request::Scan { store, .. }
+            | Request::ScanKeys { store, .. }
+            | Request::PutIf { store, .. }
+            | Request::PutTtl { store, .. }
+            | Request::Subscribe { store }
+            | Request::Commit { store, .. } => store,
+            Request::Goodbye
+            | Request::Ping
+            | Request::ServerInfo
+            | Request::ListStores
+            | Request::EnableChecksums => 0,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Request::Put { .. } => "put",
+            Request::Get { .. } => "get",
+            Request::Delete { .. } => "delete",
+            Request::Write { .. } => "write",
+            Request::PutMany { .. } => "put_many",
+            Request::Scan { .. } => "scan",
+            Request::ScanKeys { .. } => "scan_keys",
+            Request::PutIf { .. } => "put_if",
+            Request::PutTtl { .. } => "put_with_ttl",
+            Request::Goodbye => "goodbye",
+            Request::Ping => "ping",
+            Request::ServerInfo => "server_info",
+            Request::Subscribe { .. } => "subscribe",
+            Request::Commit { .. } => "commit",
+            Request::ListStores => "list_stores",
+            Request::EnableChecksums => "enable_checksums",
+        }
+    }
+
+    /// Total key bytes carried by the request (the prefix, for scans).
+    pub fn key_len(&self) -> usize {
+        match self {
+            Request::Put { key, .. }
+            | Request::Get { key, .. }
+            | Request::Delete { key, .. }
+            | Request::PutIf { key, .. }
+            | Request::PutTtl { key, .. } => key.len(),
+            Request::Scan { prefix, .. } | Request::ScanKeys { prefix, .. } => prefix.len(),
+            Request::PutMany { pairs, .. } => pairs.iter().map(|(key, _)| key.len()).sum(),
+            Request::Commit { ops, .. } => ops.iter().map(|op| op.key().len()).sum(),
+            Request::Write { .. }
+            | Request::Goodbye
+            | Request::Ping
+            | Request::ServerInfo
+            | Request::Subscribe { .. }
+            | Request::ListStores
+            | Request::EnableChecksums => 0,
+        }
+    }
+
+    /// Total value bytes carried by the request.
+    pub fn value_len(&self) -> usize {
+        match self {
+            Request::Put { value, .. } | Request::PutIf { value, .. } | Request::PutTtl { value, .. } => {
+                value.len()
+            }
+            Request::PutMany { pairs, .. } => pairs.iter().map(|(_, value)| value.len()).sum(),
+            Request::Commit { ops, .. } => ops.iter().filter_map(TxnOp::value).map(<[u8]>::len).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Whether resending the same bytes after a dropped connection is
+    /// harmless if the server had already applied the first copy.
+    pub fn is_idempotent(&self) -> bool {
+        !matches!(self, Request::PutIf { .. })
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, ProtocolError> {
+        let mut out = vec![self.opcode(), self.store()];
+        match *self {
+            Request::Put { key, value, .. } => {
+                out.extend_from_slice(key);
+                put_prefixed(&mut out, value)?;
+            }
+            Request::Get { key, .. } | Request::Delete { key, .. } => out.extend_from_slice(key),
+            Request::Write { .. }
+            | Request::Goodbye
+            | Request::Ping
+            | Request::ServerInfo
+            | Request::Subscribe { .. }
+            | Request::ListStores
+            | Request::EnableChecksums => {}
+            Request::PutMany { pairs, .. } => {
+                out.extend_from_slice(&wire_len(pairs.len())?);
+                for (key, value) in pairs {
+                    put_prefixed(&mut out, key)?;
+                    put_prefixed(&mut out, value)?;
+                }
+            }
+            Request::Scan { prefix, .. } | Request::ScanKeys { prefix, .. } => put_prefixed(&mut out, prefix)?,
+            Request::PutIf {
+                key, expected, value, ..
+            } => {
+                put_prefixed(&mut out, key)?;
+                match expected {
+                    Some(expected) => {
+                        out.push(1);
+                        put_prefixed(&mut out, expected)?;
+                    }
+                    None => out.push(0),
+                }
+                put_prefixed(&mut out, value)?;
+            }
+            Request::PutTtl {
+                key, value, ttl_secs, ..
+            } => {
+                put_prefixed(&mut out, key)?;
+                put_prefixed(&mut out, value)?;
+                out.extend_from_slice(&ttl_secs.to_be_bytes());
+            }
+            Request::Commit { ops, .. } => {
+                out.extend_from_slice(&wire_len(ops.len())?);
+                for op in ops {
+                    match op {
+                        TxnOp::Put { key, value } => {
+                            out.push(OP_PUT);
+                            put_prefixed(&mut out, key)?;
+                            put_prefixed(&mut out, value)?;
+                        }
+                        TxnOp::Delete { key } => {
+                            out.push(OP_DELETE);
+                            put_prefixed(&mut out, key)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Appends the request wrapped in an `OP_FRAMED` envelope. Nothing is
+    /// appended when encoding fails.
+    pub fn encode_framed(&self, out: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        let inner = self.encode()?;
+        let len = wire_len(inner.len())?;
+        out.extend_from_slice(&[OP_FRAMED, self.store()]);
+        out.extend_from_slice(&len);
+        out.extend_from_slice(&inner);
+        Ok(())
+    }
+}
+
+/// Everything of a PUT request up to the value itself, for callers that
+/// stream the value separately.
+pub fn encode_put_header(store: u8, key: &[u8], value_len: u32) -> Vec<u8> {
+    let mut out = vec![OP_PUT, store];
+    out.extend_from_slice(key);
+    out.extend_from_slice(&value_len.to_be_bytes());
+    out
+}
+
+fn wire_len(len: usize) -> Result<[u8; 4], ProtocolError> {
+    u32::try_from(len)
+        .map(u32::to_be_bytes)
+        .map_err(|_| ProtocolError::TooLong(len))
+}
+
+fn put_prefixed(out: &mut Vec<u8>, bytes: &[u8]) -> Result<(), ProtocolError> {
+    out.extend_from_slice(&wire_len(bytes.len())?);
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+/// A response frame borrowed from the receive buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Response<'a> {
+    pub status: Status,
+    pub payload: &'a [u8],
+}
+
+impl<'a> Response<'a> {
+    /// Parses exactly one complete frame. Never panics: short buffers,
+    /// lengths past the end, unknown statuses and trailing bytes are all
+    /// reported as errors.
+    pub fn parse(frame: &'a [u8]) -> Result<Self, ProtocolError> {
+        let mut reader = WireReader::new(frame);
+        let status = reader.u8()?;
+        let payload = reader.prefixed()?;
+        reader.finish()?;
+        Ok(Self {
+            status: Status::from_byte(status)?,
+            payload,
+        })
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, ProtocolError> {
+        let mut out = vec![self.status.as_byte()];
+        put_prefixed(&mut out, self.payload)?;
+        Ok(out)
+    }
+}
+
+/// Reads the payload length out of a response header.
+pub fn payload_len(header: &[u8; RESPONSE_HEADER_LEN]) -> usize {
+    u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize
+}
+
+// CRC-32/ISO-HDLC, the one zlib and Ethernet use: reflected polynomial
+// 0xedb88320, initial value and final xor 0xffffffff.
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Checksum used by `OP_ENABLE_CHECKSUMS` connections, sent big-endian.
+///
+/// Reference values: `""` is `0x00000000`, `"123456789"` is `0xcbf43926`,
+/// the GET request `02 01 "k"` is `0x3fbe84ed`, the PING request `0c 00` is
+/// `0xed6c5df3` and its answer `01 00 00 00 00` is `0xfb42dead`.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(bytes);
+    crc.finish()
+}
+
+/// [`crc32`] over data that arrives in pieces.
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self(!0)
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = CRC32_TABLE[((self.0 ^ u32::from(byte)) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Appends the checksum of `out[start..]`, which must hold one complete
+/// request.
+pub fn append_checksum(out: &mut Vec<u8>, start: usize) {
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Decodes a SCAN payload. An empty payload means no entries.
+pub fn decode_entries(payload: &[u8]) -> Result<Vec<KvPair>, ProtocolError> {
+    if payload.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut reader = WireReader::new(payload);
+    let count = reader.u32()? as usize;
+    // Each entry is at least 8 bytes, which bounds the preallocation for a
+    // bogus count.
+    let mut entries = Vec::with_capacity(count.min(reader.remaining() / 8));
+    for _ in 0..count {
+        let key = reader.prefixed()?;
+        let value = reader.prefixed()?;
+        entries.push((key.to_vec(), value.to_vec()));
+    }
+    reader.finish()?;
+    Ok(entries)
+}
+
+/// Decodes a SCAN_KEYS payload. An empty payload means no keys.
+pub fn decode_keys(payload: &[u8]) -> Result<Vec<Vec<u8>>, ProtocolError> {
+    if payload.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut reader = WireReader::new(payload);
+    let count = reader.u32()? as usize;
+    let mut keys = Vec::with_capacity(count.min(reader.remaining() / 4));
+    for _ in 0..count {
+        keys.push(reader.prefixed()?.to_vec());
+    }
+    reader.finish()?;
+    Ok(keys)
+}
+
+/// Decodes a LIST_STORES payload. An empty payload means no stores.
+pub fn decode_stores(payload: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+    if payload.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut reader = WireReader::new(payload);
+    let count = reader.u32()? as usize;
+    let mut stores = Vec::with_capacity(count.min(reader.remaining()));
+    for _ in 0..count {
+        stores.push(reader.u8()?);
+    }
+    reader.finish()?;
+    Ok(stores)
+}
+
+/// What an `OP_SERVER_INFO` response reports about the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerInfo {
+    pub protocol_version: u16,
+    /// Free-form, for logs and diagnostics only.
+    pub version: String,
+}
+
+impl ServerInfo {
+    pub fn decode(payload: &[u8]) -> Result<Self, ProtocolError> {
+        let mut reader = WireReader::new(payload);
+        let protocol_version = reader.u16()?;
+        let version = String::from_utf8_lossy(reader.prefixed()?).into_owned();
+        reader.finish()?;
+        Ok(Self {
+            protocol_version,
+            version,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Put,
+    Delete,
+    Write,
+}
+
+impl EventKind {
+    pub fn from_byte(byte: u8) -> Result<Self, ProtocolError> {
+        match byte {
+            EVENT_PUT => Ok(EventKind::Put),
+            EVENT_DELETE => Ok(EventKind::Delete),
+            EVENT_WRITE => Ok(EventKind::Write),
+            other => Err(ProtocolError::UnknownEvent(other)),
+        }
+    }
+
+    pub fn as_byte(self) -> u8 {
+        match self {
+            EventKind::Put => EVENT_PUT,
+            EventKind::Delete => EVENT_DELETE,
+            EventKind::Write => EVENT_WRITE,
+        }
+    }
+}
+
+/// A change pushed to a subscribed connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub store: u8,
+    pub key: Vec<u8>,
+}
+
+impl Event {
+    /// Parses exactly one complete event frame, with the same guarantees as
+    /// `Response::parse`.
+    pub fn decode(frame: &[u8]) -> Result<Self, ProtocolError> {
+        let mut reader = WireReader::new(frame);
+        let kind = reader.u8()?;
+        let store = reader.u8()?;
+        let key = reader.prefixed()?;
+        reader.finish()?;
+        Ok(Self {
+            kind: EventKind::from_byte(kind)?,
+            store,
+            key: key.to_vec(),
+        })
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, ProtocolError> {
+        let mut out = vec![self.kind.as_byte(), self.store];
+        put_prefixed(&mut out, &self.key)?;
+        Ok(out)
+    }
+}
+
+/// Reads the key length out of an event header.
+pub fn event_key_len(header: &[u8; EVENT_HEADER_LEN]) -> usize {
+    u32::from_be_bytes([header[2], header[3], header[4], header[5]]) as usize
+}
+
+/// Bounds-checked cursor over a received buffer.
+pub struct WireReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> WireReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], ProtocolError> {
+        let truncated = ProtocolError::Truncated {
+            offset: self.pos,
+            needed: len,
+            available: self.remaining(),
+        };
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or(truncated)?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, ProtocolError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, ProtocolError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, ProtocolError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// A u32 big-endian length followed by that many bytes.
+    pub fn prefixed(&mut self) -> Result<&'a [u8], ProtocolError> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+
+    pub fn finish(self) -> Result<(), ProtocolError> {
+        match self.remaining() {
+            0 => Ok(()),
+            extra => Err(ProtocolError::TrailingBytes(extra)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift64*, so the mutation loop is random-looking but reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    fn entries_payload(entries: &[(&[u8], &[u8])]) -> Vec<u8> {
+        let mut out = (entries.len() as u32).to_be_bytes().to_vec();
+        for (key, value) in entries {
+            put_prefixed(&mut out, key).unwrap();
+            put_prefixed(&mut out, value).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn response_encode_parse_round_trip() {
+        for status in [
+            Status::Success,
+            Status::Error,
+            Status::NotFound,
+            Status::ConditionFailed,
+            Status::Unsupported,
+        ] {
+            for payload in [&b""[..], b"value", &[0u8, 0xff, b'\\']] {
+                let frame = Response { status, payload }.encode().unwrap();
+                assert_eq!(frame.len(), RESPONSE_HEADER_LEN + payload.len());
+                assert_eq!(Response::parse(&frame).unwrap(), Response { status, payload });
+            }
+        }
+    }
+
+    #[test]
+    fn requests_encode_to_the_documented_layout() {
+        assert_eq!(Request::Get { store: 1, key: b"k" }.encode().unwrap(), [OP_GET, 1, b'k']);
+        assert_eq!(
+            Request::Put { store: 2, key: b"k", value: b"vv" }.encode().unwrap(),
+            [OP_PUT, 2, b'k', 0, 0, 0, 2, b'v', b'v']
+        );
+        assert_eq!(
+            Request::Scan { store: 3, prefix: b"p" }.encode().unwrap(),
+            [OP_SCAN, 3, 0, 0, 0, 1, b'p']
+        );
+        assert_eq!(
+            Request::PutIf {
+                store: 1,
+                key: b"k",
+                expected: Some(b"o"),
+                value: b"n",
+            }
+            .encode()
+            .unwrap(),
+            [OP_PUT_IF, 1, 0, 0, 0, 1, b'k', 1, 0, 0, 0, 1, b'o', 0, 0, 0, 1, b'n']
+        );
+
+        let mut framed = Vec::new();
+        Request::Get { store: 1, key: b"k" }.encode_framed(&mut framed).unwrap();
+        assert_eq!(framed, [OP_FRAMED, 1, 0, 0, 0, 3, OP_GET, 1, b'k']);
+    }
+
+    #[test]
+    fn truncated_payload_is_reported_with_its_offset() {
+        assert_eq!(
+            Response::parse(&[STATUS_SUCCESS, 0, 0, 0, 5, b'a']),
+            Err(ProtocolError::Truncated {
+                offset: 5,
+                needed: 5,
+                available: 1,
+            })
+        );
+        assert!(matches!(
+            Response::parse(&[STATUS_SUCCESS, 0, 0]),
+            Err(ProtocolError::Truncated { offset: 1, .. })
+        ));
+        assert!(matches!(Response::parse(&[]), Err(ProtocolError::Truncated { offset: 0, .. })));
+        // A length near u32::MAX must not wrap the end offset.
+        assert!(matches!(
+            Response::parse(&[STATUS_SUCCESS, 0xff, 0xff, 0xff, 0xff, 0]),
+            Err(ProtocolError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn unknown_status_and_trailing_bytes_are_errors() {
+        assert_eq!(Response::parse(&[9, 0, 0, 0, 0]), Err(ProtocolError::UnknownStatus(9)));
+        assert_eq!(
+            Response::parse(&[STATUS_SUCCESS, 0, 0, 0, 0, 7]),
+            Err(ProtocolError::TrailingBytes(1))
+        );
+    }
+
+    #[test]
+    fn entries_keys_and_stores_decode() {
+        let entries: [(&[u8], &[u8]); 2] = [(b"a", b"1"), (b"bb", b"")];
+        assert_eq!(
+            decode_entries(&entries_payload(&entries)).unwrap(),
+            vec![(b"a".to_vec(), b"1".to_vec()), (b"bb".to_vec(), Vec::new())]
+        );
+        assert_eq!(decode_entries(&[]).unwrap(), Vec::new());
+        assert_eq!(decode_entries(&[0, 0, 0, 0]).unwrap(), Vec::new());
+
+        assert_eq!(decode_keys(&[0, 0, 0, 1, 0, 0, 0, 1, b'k']).unwrap(), vec![b"k".to_vec()]);
+        assert_eq!(decode_stores(&[0, 0, 0, 2, 1, 7]).unwrap(), vec![1, 7]);
+    }
+
+    #[test]
+    fn entry_count_past_the_payload_is_truncated() {
+        let mut payload = entries_payload(&[(b"a", b"1")]);
+        payload[3] = 2;
+        assert!(matches!(decode_entries(&payload), Err(ProtocolError::Truncated { .. })));
+        // A bogus count must fail, not allocate room for four billion entries.
+        assert!(matches!(
+            decode_entries(&[0xff, 0xff, 0xff, 0xff]),
+            Err(ProtocolError::Truncated { .. })
+        ));
+        assert!(matches!(
+            decode_keys(&[0xff, 0xff, 0xff, 0xff]),
+            Err(ProtocolError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn event_encode_decode_round_trip() {
+        for kind in [EventKind::Put, EventKind::Delete, EventKind::Write] {
+            let event = Event {
+                kind,
+                store: 4,
+                key: b"key".to_vec(),
+            };
+            let frame = event.encode().unwrap();
+            let header: [u8; EVENT_HEADER_LEN] = frame[..EVENT_HEADER_LEN].try_into().unwrap();
+            assert_eq!(event_key_len(&header), 3);
+            assert_eq!(Event::decode(&frame).unwrap(), event);
+        }
+        assert_eq!(Event::decode(&[9, 0, 0, 0, 0, 0]), Err(ProtocolError::UnknownEvent(9)));
+    }
+
+    #[test]
+    fn server_info_decodes() {
+        let mut payload = PROTOCOL_VERSION.to_be_bytes().to_vec();
+        put_prefixed(&mut payload, b"scalerize 1.0").unwrap();
+        assert_eq!(
+            ServerInfo::decode(&payload).unwrap(),
+            ServerInfo {
+                protocol_version: PROTOCOL_VERSION,
+                version: "scalerize 1.0".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn wire_reader_rejects_lengths_past_the_end() {
+        let mut reader = WireReader::new(b"abc");
+        assert!(reader.bytes(usize::MAX).is_err());
+        assert_eq!(reader.bytes(2).unwrap(), b"ab");
+        assert!(reader.u16().is_err());
+        assert_eq!(reader.remaining(), 1);
+        assert_eq!(reader.finish(), Err(ProtocolError::TrailingBytes(1)));
+    }
+
+    #[test]
+    fn crc32_matches_the_reference_vectors() {
+        assert_eq!(crc32(b""), 0x0000_0000);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(&[OP_GET, 1, b'k']), 0x3fbe_84ed);
+        assert_eq!(crc32(&[OP_PING, 0]), 0xed6c_5df3);
+        assert_eq!(crc32(&[STATUS_SUCCESS, 0, 0, 0, 0]), 0xfb42_dead);
+    }
+
+    #[test]
+    fn crc32_can_be_fed_in_pieces() {
+        let mut checksum = Crc32::new();
+        for piece in [&b"1234"[..], b"", b"56789"] {
+            checksum.update(piece);
+        }
+        assert_eq!(checksum.finish(), crc32(b"123456789"));
+        assert_eq!(Crc32::default().finish(), crc32(b""));
+    }
+
+    #[test]
+    fn append_checksum_covers_only_the_last_frame() {
+        let mut out = vec![0xaa, 0xbb];
+        out.extend_from_slice(&Request::Ping.encode().unwrap());
+        append_checksum(&mut out, 2);
+        assert_eq!(out, [0xaa, 0xbb, OP_PING, 0, 0xed, 0x6c, 0x5d, 0xf3]);
+    }
+
+    #[test]
+    fn decoders_never_panic_on_mutated_input() {
+        let seeds: Vec<Vec<u8>> = vec![
+            Response {
+                status: Status::Success,
+                payload: b"value",
+            }
+            .encode()
+            .unwrap(),
+            entries_payload(&[(b"a", b"1"), (b"bb", b"22")]),
+            [0, 0, 0, 1, 0, 0, 0, 1, b'k'].to_vec(),
+            Event {
+                kind: EventKind::Put,
+                store: 1,
+                key: b"key".to_vec(),
+            }
+            .encode()
+            .unwrap(),
+            Vec::new(),
+        ];
+        let mut rng = Rng(0x5ca1_ab1e_d00d_f00d);
+
+        for _ in 0..20_000 {
+            let mut input = seeds[rng.below(seeds.len())].clone();
+            for _ in 0..1 + rng.below(4) {
+                match rng.below(4) {
+                    0 if !input.is_empty() => {
+                        let at = rng.below(input.len());
+                        input[at] = rng.next() as u8;
+                    }
+                    1 if !input.is_empty() => input.truncate(rng.below(input.len())),
+                    2 => input.push(rng.next() as u8),
+                    // Plant a length field that points anywhere.
+                    _ if input.len() >= 5 => {
+                        let at = rng.below(input.len() - 3);
+                        input[at..at + 4].copy_from_slice(&(rng.next() as u32).to_be_bytes());
+                    }
+                    _ => {}
+                }
+            }
+
+            let _ = Response::parse(&input);
+            let _ = decode_entries(&input);
+            let _ = decode_keys(&input);
+            let _ = decode_stores(&input);
+            let _ = ServerInfo::decode(&input);
+            let _ = Event::decode(&input);
+            let mut reader = WireReader::new(&input);
+            while reader.prefixed().is_ok() {}
+            let _ = reader.u8();
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::client::{ClientError, ScalerizeClient};
+
+/// A [`ScalerizeClient`] bound to one store, from [`ScalerizeClient::store`].
+///
+/// Each method is the client method of the same name with the store number
+/// filled in. The handle is just the borrow and the store byte, so making a
+/// fresh one per store inside a loop costs nothing.
+pub struct StoreHandle<'a> {
+    client: &'a mut ScalerizeClient,
+    store_number: u8,
+}
+
+impl<'a> StoreHandle<'a> {
+    pub(crate) fn new(client: &'a mut ScalerizeClient, store_number: u8) -> Self {
+        Self { client, store_number }
+    }
+
+    pub fn store_number(&self) -> u8 {
+        self.store_number
+    }
+
+    pub fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, ClientError> {
+        self.client.get(self.store_number, key)
+    }
+
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), ClientError> {
+        self.client.put(self.store_number, key, value)
+    }
+
+    pub fn delete(&mut self, key: &[u8]) -> Result<(), ClientError> {
+        self.client.delete(self.store_number, key)
+    }
+
+    pub fn write(&mut self) -> Result<(), ClientError> {
+        self.client.write(self.store_number)
+    }
+}
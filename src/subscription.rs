@@ -0,0 +1,51 @@
+use crate::client::{ClientError, ScalerizeClient};
+use crate::protocol::Event;
+
+/// A connection dedicated to the change events of one store, opened with
+/// [`ScalerizeClient::subscribe`].
+///
+/// Iterating yields events until the server ends the subscription by
+/// closing the connection. With a read timeout configured, an idle
+/// subscription reports `ClientError::Timeout` and stays usable; any other
+/// error ends it.
+pub struct Subscription {
+    client: ScalerizeClient,
+    store: u8,
+    finished: bool,
+}
+
+impl Subscription {
+    pub(crate) fn new(client: ScalerizeClient, store: u8) -> Self {
+        Self {
+            client,
+            store,
+            finished: false,
+        }
+    }
+
+    pub fn store(&self) -> u8 {
+        self.store
+    }
+
+    /// Blocks until the next event arrives. `Ok(None)` means the
+    /// subscription has ended.
+    pub fn next_event(&mut self) -> Result<Option<Event>, ClientError> {
+        if self.finished {
+            return Ok(None);
+        }
+        let result = self.client.read_event();
+        self.finished = match &result {
+            Ok(event) => event.is_none(),
+            Err(e) => !matches!(e, ClientError::Timeout),
+        };
+        result
+    }
+}
+
+impl Iterator for Subscription {
+    type Item = Result<Event, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
@@ -0,0 +1,497 @@
+//! In-process mock of the scalerize server for tests and benches.
+//!
+//! [`MockServer::start`] listens on a fresh socket under the temp directory
+//! and answers every opcode in [`protocol`](crate::protocol) from an
+//! in-memory map, one thread per connection. Like the real server, it takes
+//! an unprefixed GET or DELETE key to be everything the client wrote in one
+//! go, so pipelined requests need the `OP_FRAMED` envelope. Dropping the
+//! server hangs up on every connection still open.
+//!
+//! Connections may switch on checksums. In that mode an unprefixed request
+//! is only acted on once its trailer checks out, as a partial read and a
+//! corrupt request look the same; a corrupt `OP_FRAMED` request is answered
+//! with an error.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::client::{ClientError, DEFAULT_MAX_KEY_SIZE};
+use crate::options::ClientOptions;
+use crate::protocol::*;
+use crate::ScalerizeClient;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+struct Entry {
+    value: Vec<u8>,
+    expires: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Shared {
+    data: Mutex<BTreeMap<(u8, Vec<u8>), Entry>>,
+    subscribers: Mutex<Vec<(u8, UnixStream)>>,
+    // Open connections by id, so dropping the server can hang up on them.
+    connections: Mutex<BTreeMap<usize, UnixStream>>,
+    refuse_checksums: AtomicBool,
+    corrupt_checksums: AtomicBool,
+}
+
+enum Step {
+    Incomplete,
+    Answer { consumed: usize, response: Vec<u8> },
+    Subscribe { store: u8, response: Vec<u8> },
+    EnableChecksums { consumed: usize },
+}
+
+pub struct MockServer {
+    path: PathBuf,
+    shared: Arc<Shared>,
+    stop: Arc<AtomicBool>,
+    accept: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    pub fn start() -> std::io::Result<Self> {
+        Self::start_at(std::env::temp_dir().join(format!(
+            "scalerize-mock-{}-{}.sock",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        )))
+    }
+
+    /// Listens on `path`, replacing any socket file already there. Starting
+    /// a new server on the path of a dropped one stands in for a restart.
+    pub fn start_at(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+
+        let shared = Arc::new(Shared::default());
+        let stop = Arc::new(AtomicBool::new(false));
+        let accept = {
+            let shared = Arc::clone(&shared);
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || {
+                for (id, stream) in listener.incoming().enumerate() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    if let Ok(handle) = stream.try_clone() {
+                        lock(&shared.connections).insert(id, handle);
+                    }
+                    let shared = Arc::clone(&shared);
+                    std::thread::spawn(move || serve(&shared, stream, id));
+                }
+            })
+        };
+
+        Ok(Self {
+            path,
+            shared,
+            stop,
+            accept: Some(accept),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn connect(&self) -> Result<ScalerizeClient, ClientError> {
+        ScalerizeClient::connect_to(&self.path)
+    }
+
+    pub fn connect_with(&self, options: ClientOptions) -> Result<ScalerizeClient, ClientError> {
+        ScalerizeClient::connect_with(&self.path, options)
+    }
+
+    /// Whether later `OP_ENABLE_CHECKSUMS` requests are accepted. On by
+    /// default; when off, the mock answers like a server without checksums.
+    pub fn set_checksums(&self, supported: bool) {
+        self.shared.refuse_checksums.store(!supported, Ordering::SeqCst);
+    }
+
+    /// Sends a wrong checksum after every later response on connections
+    /// that have checksums on.
+    pub fn corrupt_checksums(&self, corrupt: bool) {
+        self.shared.corrupt_checksums.store(corrupt, Ordering::SeqCst);
+    }
+
+    /// The live value stored under `key`, bypassing the socket.
+    pub fn value(&self, store: u8, key: &[u8]) -> Option<Vec<u8>> {
+        live(&mut lock(&self.shared.data), store, key).cloned()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wakes the accept loop so it sees the flag.
+        let _ = UnixStream::connect(&self.path);
+        if let Some(accept) = self.accept.take() {
+            let _ = accept.join();
+        }
+        for (_, stream) in std::mem::take(&mut *lock(&self.shared.connections)) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Shutting the socket down, rather than only dropping this handle, also ends
+// the copy kept for a subscriber, so the client sees EOF either way.
+fn serve(shared: &Shared, mut stream: UnixStream, id: usize) {
+    serve_requests(shared, &mut stream);
+    lock(&shared.connections).remove(&id);
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+fn serve_requests(shared: &Shared, stream: &mut UnixStream) {
+    let mut buffer = Vec::new();
+    let mut chunk = vec![0u8; 1024 * 1024];
+    let mut checksums = false;
+    loop {
+        let n = match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        buffer.extend_from_slice(&chunk[..n]);
+
+        while !buffer.is_empty() {
+            let next = if checksums {
+                checked_step(shared, &buffer)
+            } else {
+                step(shared, &buffer)
+            };
+            match next {
+                Step::Incomplete => break,
+                Step::Answer { consumed, mut response } => {
+                    if checksums {
+                        seal(shared, &mut response);
+                    }
+                    if stream.write_all(&response).is_err() {
+                        return;
+                    }
+                    buffer.drain(..consumed);
+                }
+                Step::EnableChecksums { consumed } => {
+                    if stream.write_all(&success(&[])).is_err() {
+                        return;
+                    }
+                    checksums = true;
+                    buffer.drain(..consumed);
+                }
+                Step::Subscribe { store, mut response } => {
+                    if checksums {
+                        seal(shared, &mut response);
+                    }
+                    // Registered before answering, so the client cannot make
+                    // a change it would miss once subscribe returns.
+                    if let Ok(clone) = stream.try_clone() {
+                        lock(&shared.subscribers).push((store, clone));
+                    }
+                    if stream.write_all(&response).is_err() {
+                        return;
+                    }
+                    // Nothing but events goes out on this connection now; wait
+                    // for the client to hang up.
+                    while matches!(stream.read(&mut chunk), Ok(n) if n > 0) {}
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn step(shared: &Shared, buffer: &[u8]) -> Step {
+    if buffer.len() < 2 {
+        return Step::Incomplete;
+    }
+    let (op, store) = (buffer[0], buffer[1]);
+    match op {
+        OP_GET => answer(buffer.len(), get(shared, store, &buffer[2..])),
+        OP_DELETE => {
+            delete(shared, store, &buffer[2..]);
+            answer(buffer.len(), success(&[]))
+        }
+        OP_PUT => match split_put(buffer) {
+            Some((key, value)) => {
+                put(shared, store, key, value, None);
+                answer(buffer.len(), success(&[]))
+            }
+            None => Step::Incomplete,
+        },
+        OP_FRAMED => {
+            let mut reader = WireReader::new(&buffer[2..]);
+            let inner = match reader.prefixed() {
+                Ok(inner) => inner,
+                Err(_) => return Step::Incomplete,
+            };
+            let consumed = buffer.len() - reader.remaining();
+            // The envelope says the inner request is complete, so anything
+            // short of an answer is a malformed request.
+            match step(shared, inner) {
+                Step::Answer { response, .. } => answer(consumed, response),
+                _ => answer(consumed, error(b"malformed framed request")),
+            }
+        }
+        OP_SUBSCRIBE => Step::Subscribe {
+            store,
+            response: success(&[]),
+        },
+        OP_ENABLE_CHECKSUMS if !shared.refuse_checksums.load(Ordering::SeqCst) => Step::EnableChecksums { consumed: 2 },
+        _ => {
+            let mut reader = WireReader::new(&buffer[2..]);
+            match prefixed_op(shared, op, store, &mut reader) {
+                Ok(response) => answer(buffer.len() - reader.remaining(), response),
+                Err(ProtocolError::Truncated { .. }) => Step::Incomplete,
+                Err(e) => answer(buffer.len(), error(e.to_string().as_bytes())),
+            }
+        }
+    }
+}
+
+// Like `step`, for a connection with checksums on: the request ends where
+// its OP_FRAMED envelope says, or else four bytes before the end of the
+// buffer, and is followed by its CRC-32.
+fn checked_step(shared: &Shared, buffer: &[u8]) -> Step {
+    let framed = buffer[0] == OP_FRAMED;
+    let end = if framed {
+        let mut reader = WireReader::new(buffer.get(2..).unwrap_or_default());
+        if reader.prefixed().is_err() {
+            return Step::Incomplete;
+        }
+        buffer.len() - reader.remaining()
+    } else {
+        match buffer.len().checked_sub(CHECKSUM_LEN) {
+            Some(end) if end >= 2 => end,
+            _ => return Step::Incomplete,
+        }
+    };
+    let Some(trailer) = buffer.get(end..end + CHECKSUM_LEN) else {
+        return Step::Incomplete;
+    };
+    let consumed = end + CHECKSUM_LEN;
+    if crc32(&buffer[..end]).to_be_bytes() != trailer {
+        return if framed {
+            answer(consumed, error(b"request checksum mismatch"))
+        } else {
+            Step::Incomplete
+        };
+    }
+
+    match step(shared, &buffer[..end]) {
+        Step::Answer { consumed: used, response } if used == end => answer(consumed, response),
+        Step::Answer { .. } | Step::Incomplete => answer(consumed, error(b"malformed request")),
+        Step::EnableChecksums { .. } => Step::EnableChecksums { consumed },
+        subscribe @ Step::Subscribe { .. } => subscribe,
+    }
+}
+
+fn seal(shared: &Shared, response: &mut Vec<u8>) {
+    append_checksum(response, 0);
+    if shared.corrupt_checksums.load(Ordering::SeqCst) {
+        let last = response.len() - 1;
+        response[last] ^= 0x01;
+    }
+}
+
+fn prefixed_op(shared: &Shared, op: u8, store: u8, reader: &mut WireReader<'_>) -> Result<Vec<u8>, ProtocolError> {
+    match op {
+        OP_WRITE | OP_GOODBYE | OP_PING => {
+            if op == OP_WRITE {
+                notify(shared, EventKind::Write, store, &[]);
+            }
+            Ok(success(&[]))
+        }
+        OP_SERVER_INFO => {
+            let version = concat!("scalerize-mock ", env!("CARGO_PKG_VERSION"));
+            let mut payload = PROTOCOL_VERSION.to_be_bytes().to_vec();
+            push_prefixed(&mut payload, version.as_bytes());
+            Ok(success(&payload))
+        }
+        OP_LIST_STORES => {
+            let mut stores: Vec<u8> = lock(&shared.data).keys().map(|(store, _)| *store).collect();
+            stores.dedup();
+            let mut payload = wire_len(stores.len()).to_vec();
+            payload.extend_from_slice(&stores);
+            Ok(success(&payload))
+        }
+        OP_PUT_MANY => {
+            let count = reader.u32()?;
+            let mut pairs = Vec::new();
+            for _ in 0..count {
+                pairs.push((reader.prefixed()?, reader.prefixed()?));
+            }
+            for (key, value) in pairs {
+                put(shared, store, key, value, None);
+            }
+            Ok(success(&[]))
+        }
+        OP_SCAN | OP_SCAN_KEYS => {
+            let prefix = reader.prefixed()?;
+            let data = lock(&shared.data);
+            let now = Instant::now();
+            let matching: Vec<_> = data
+                .range((store, prefix.to_vec())..)
+                .take_while(|((entry_store, key), _)| *entry_store == store && key.starts_with(prefix))
+                .filter(|(_, entry)| entry.expires.is_none_or(|expires| expires > now))
+                .map(|((_, key), entry)| (key.clone(), entry.value.clone()))
+                .collect();
+            let mut payload = wire_len(matching.len()).to_vec();
+            for (key, value) in &matching {
+                push_prefixed(&mut payload, key);
+                if op == OP_SCAN {
+                    push_prefixed(&mut payload, value);
+                }
+            }
+            drop(data);
+            Ok(success(&payload))
+        }
+        OP_PUT_IF => {
+            let key = reader.prefixed()?;
+            let expected = match reader.u8()? {
+                0 => None,
+                _ => Some(reader.prefixed()?),
+            };
+            let value = reader.prefixed()?;
+            let current = live(&mut lock(&shared.data), store, key).cloned();
+            if current.as_deref() != expected {
+                return Ok(status(Status::ConditionFailed, &[]));
+            }
+            put(shared, store, key, value, None);
+            Ok(success(&[]))
+        }
+        OP_PUT_TTL => {
+            let key = reader.prefixed()?;
+            let value = reader.prefixed()?;
+            let ttl = reader.bytes(8)?;
+            let ttl = u64::from_be_bytes(ttl.try_into().expect("8 bytes"));
+            put(shared, store, key, value, Some(Duration::from_secs(ttl)));
+            Ok(success(&[]))
+        }
+        OP_COMMIT => {
+            let count = reader.u32()?;
+            let mut ops = Vec::new();
+            for index in 0..count {
+                let kind = reader.u8()?;
+                let key = reader.prefixed()?;
+                match kind {
+                    OP_PUT => ops.push((key, Some(reader.prefixed()?))),
+                    OP_DELETE => ops.push((key, None)),
+                    _ => {
+                        let mut payload = index.to_be_bytes().to_vec();
+                        payload.extend_from_slice(b"only puts and deletes can be committed");
+                        return Ok(error(&payload));
+                    }
+                }
+            }
+            for (key, value) in ops {
+                match value {
+                    Some(value) => put(shared, store, key, value, None),
+                    None => delete(shared, store, key),
+                }
+            }
+            Ok(success(&[]))
+        }
+        _ => Ok(status(Status::Unsupported, &[])),
+    }
+}
+
+// A legacy PUT carries no key length: the key is whatever makes the value
+// length line up with the end of the buffer. The longest such key wins, as
+// a value can itself look like a length field.
+fn split_put(buffer: &[u8]) -> Option<(&[u8], &[u8])> {
+    let longest = buffer.len().checked_sub(6)?.min(DEFAULT_MAX_KEY_SIZE);
+    (0..=longest).rev().find_map(|key_len| {
+        let field = &buffer[2 + key_len..6 + key_len];
+        let value_len = u32::from_be_bytes(field.try_into().expect("4 bytes"));
+        (usize::try_from(value_len).ok()? == buffer.len() - 6 - key_len).then(|| (&buffer[2..2 + key_len], &buffer[6 + key_len..]))
+    })
+}
+
+fn wire_len(len: usize) -> [u8; 4] {
+    u32::try_from(len).expect("mock payloads fit a u32 length").to_be_bytes()
+}
+
+fn push_prefixed(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&wire_len(bytes.len()));
+    out.extend_from_slice(bytes);
+}
+
+fn answer(consumed: usize, response: Vec<u8>) -> Step {
+    Step::Answer { consumed, response }
+}
+
+fn status(status: Status, payload: &[u8]) -> Vec<u8> {
+    Response { status, payload }
+        .encode()
+        .expect("mock payloads fit a u32 length")
+}
+
+fn success(payload: &[u8]) -> Vec<u8> {
+    status(Status::Success, payload)
+}
+
+fn error(message: &[u8]) -> Vec<u8> {
+    status(Status::Error, message)
+}
+
+fn live<'a>(data: &'a mut BTreeMap<(u8, Vec<u8>), Entry>, store: u8, key: &[u8]) -> Option<&'a Vec<u8>> {
+    let id = (store, key.to_vec());
+    if data.get(&id)?.expires.is_some_and(|expires| expires <= Instant::now()) {
+        data.remove(&id);
+        return None;
+    }
+    data.get(&id).map(|entry| &entry.value)
+}
+
+fn get(shared: &Shared, store: u8, key: &[u8]) -> Vec<u8> {
+    match live(&mut lock(&shared.data), store, key) {
+        Some(value) => success(value),
+        None => status(Status::NotFound, &[]),
+    }
+}
+
+fn put(shared: &Shared, store: u8, key: &[u8], value: &[u8], ttl: Option<Duration>) {
+    let entry = Entry {
+        value: value.to_vec(),
+        // A TTL too far out for an Instant never expires.
+        expires: ttl.and_then(|ttl| Instant::now().checked_add(ttl)),
+    };
+    lock(&shared.data).insert((store, key.to_vec()), entry);
+    notify(shared, EventKind::Put, store, key);
+}
+
+fn delete(shared: &Shared, store: u8, key: &[u8]) {
+    lock(&shared.data).remove(&(store, key.to_vec()));
+    notify(shared, EventKind::Delete, store, key);
+}
+
+fn notify(shared: &Shared, kind: EventKind, store: u8, key: &[u8]) {
+    let mut subscribers = lock(&shared.subscribers);
+    if subscribers.is_empty() {
+        return;
+    }
+    let event = Event {
+        kind,
+        store,
+        key: key.to_vec(),
+    }
+    .encode()
+    .expect("keys fit a u32 length");
+    subscribers.retain_mut(|(subscribed, stream)| *subscribed != store || stream.write_all(&event).is_ok());
+}
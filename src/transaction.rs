@@ -0,0 +1,58 @@
+use crate::client::{ClientError, ScalerizeClient};
+use crate::protocol::TxnOp;
+
+/// Puts and deletes on one store, applied together by the server or not at
+/// all. Started with [`ScalerizeClient::begin`].
+///
+/// Operations are only buffered until [`commit`](Txn::commit), which sends
+/// them as a single `OP_COMMIT` request. Rolling back or dropping the
+/// transaction discards them without touching the socket. When the server
+/// rejects the commit, the `OperationFailed` message names the index of the
+/// operation at fault, counting from 0 in the order they were added.
+pub struct Txn<'a> {
+    client: &'a mut ScalerizeClient,
+    store: u8,
+    ops: Vec<TxnOp>,
+}
+
+impl<'a> Txn<'a> {
+    pub(crate) fn new(client: &'a mut ScalerizeClient, store: u8) -> Self {
+        Self {
+            client,
+            store,
+            ops: Vec::new(),
+        }
+    }
+
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> &mut Self {
+        self.ops.push(TxnOp::Put {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        });
+        self
+    }
+
+    pub fn delete(&mut self, key: &[u8]) -> &mut Self {
+        self.ops.push(TxnOp::Delete { key: key.to_vec() });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Sends every buffered operation in one request. Committing an empty
+    /// transaction sends nothing.
+    pub fn commit(self) -> Result<(), ClientError> {
+        if self.ops.is_empty() {
+            return Ok(());
+        }
+        self.client.commit(self.store, &self.ops)
+    }
+
+    pub fn rollback(self) {}
+}
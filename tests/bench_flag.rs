@@ -0,0 +1,34 @@
+// `--bench` has to keep working in a default build, so this runs the binary
+// the way `cargo run -- --bench` would, narrowed to two quick benches: one
+// protocol-only and one against the mock server.
+#![cfg(all(feature = "cli", feature = "bench"))]
+
+use std::process::Command;
+
+#[test]
+fn bench_flag_runs_the_benches() {
+    let output = Command::new(env!("CARGO_BIN_EXE_unixSocketClient"))
+        .args(["--bench", "--sample-count", "1", "--sample-size", "1"])
+        .args(["decode_error_message", "bench_get_operation"])
+        .env_remove("SCALERIZE_SOCKET")
+        .output()
+        .expect("failed to run the binary");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "stdout:\n{}\nstderr:\n{}", stdout, stderr);
+    assert!(stdout.contains("in-process mock server"), "{}", stdout);
+    assert!(stdout.contains("decode_error_message"), "{}", stdout);
+    assert!(stdout.contains("bench_get_operation"), "{}", stdout);
+}
+
+#[test]
+fn without_bench_flag_the_cli_runs() {
+    let output = Command::new(env!("CARGO_BIN_EXE_unixSocketClient"))
+        .arg("--help")
+        .output()
+        .expect("failed to run the binary");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("Usage:"), "{}", stdout);
+    assert!(!stdout.contains("Running benchmarks"), "{}", stdout);
+}
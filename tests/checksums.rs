@@ -0,0 +1,170 @@
+mod common;
+
+use std::io::Cursor;
+use std::time::Duration;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::protocol::{crc32, OP_ENABLE_CHECKSUMS, OP_PING, STATUS_ERROR, STATUS_SUCCESS};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ClientOptions, Reply, ScalerizeClient};
+
+fn checksummed() -> ClientOptions {
+    ClientOptions::new().checksums(true)
+}
+
+fn sealed(mut bytes: Vec<u8>) -> Vec<u8> {
+    let crc = crc32(&bytes);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+    bytes
+}
+
+#[test]
+fn ping_with_checksums_matches_the_documented_bytes() {
+    let server = ScriptedServer::start(vec![
+        Step::Reply(frame(STATUS_SUCCESS, b"")),
+        Step::Reply(vec![0x01, 0x00, 0x00, 0x00, 0x00, 0xfb, 0x42, 0xde, 0xad]),
+    ]);
+    let mut client = ScalerizeClient::connect_with(server.path(), checksummed()).unwrap();
+    assert!(client.checksums_enabled());
+
+    client.ping().unwrap();
+    drop(client);
+    assert_eq!(
+        server.finish().bytes(),
+        [OP_ENABLE_CHECKSUMS, 0, 0x0c, 0x00, 0xed, 0x6c, 0x5d, 0xf3]
+    );
+}
+
+#[test]
+fn every_operation_round_trips_with_checksums_on() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect_with(checksummed()).unwrap();
+    assert!(client.checksums_enabled());
+
+    client.put(1, b"a", b"1").unwrap();
+    assert_eq!(client.get(1, b"a").unwrap(), Some(b"1".to_vec()));
+    assert_eq!(client.get(1, b"missing").unwrap(), None);
+    client.put_reader(1, b"big", 100_000, Cursor::new(vec![7u8; 100_000])).unwrap();
+    let mut value = Vec::new();
+    assert_eq!(client.get_writer(1, b"big", &mut value).unwrap(), Some(100_000));
+    assert_eq!(value, vec![7u8; 100_000]);
+    assert!(client.put_if(1, b"a", Some(b"1"), b"2").unwrap());
+    assert_eq!(client.scan_keys(1, None).unwrap(), [b"a".to_vec(), b"big".to_vec()]);
+
+    let mut pipeline = client.pipeline();
+    pipeline.get(1, b"a").delete(1, b"a").get(1, b"a");
+    let replies = pipeline.flush().unwrap();
+    assert_eq!(replies[0].as_ref().unwrap(), &Reply::Value(Some(b"2".to_vec())));
+    assert_eq!(replies[2].as_ref().unwrap(), &Reply::Value(None));
+
+    let mut txn = client.begin(1);
+    txn.put(b"t", b"1");
+    txn.commit().unwrap();
+    assert_eq!(server.value(1, b"t"), Some(b"1".to_vec()));
+}
+
+#[test]
+fn subscription_negotiates_its_own_connection() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect_with(checksummed()).unwrap();
+    let mut subscription = client.subscribe(1).unwrap();
+
+    client.put(1, b"a", b"1").unwrap();
+    assert_eq!(subscription.next().unwrap().unwrap().key, b"a");
+}
+
+#[test]
+fn option_off_sends_plain_frames() {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(STATUS_SUCCESS, b""))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+    assert!(!client.checksums_enabled());
+
+    client.ping().unwrap();
+    drop(client);
+    assert_eq!(server.finish().bytes(), [OP_PING, 0]);
+}
+
+#[test]
+fn server_without_checksums_turns_them_off() {
+    let server = MockServer::start().unwrap();
+    server.set_checksums(false);
+    let mut client = server.connect_with(checksummed()).unwrap();
+    assert!(!client.checksums_enabled());
+    client.put(1, b"a", b"1").unwrap();
+    assert_eq!(client.get(1, b"a").unwrap(), Some(b"1".to_vec()));
+
+    // A server that predates the opcode answers with an error.
+    let server = ScriptedServer::start(vec![
+        Step::Reply(frame(STATUS_ERROR, b"unknown opcode")),
+        Step::Reply(frame(STATUS_SUCCESS, b"")),
+    ]);
+    let mut client = ScalerizeClient::connect_with(server.path(), checksummed()).unwrap();
+    assert!(!client.checksums_enabled());
+    client.ping().unwrap();
+}
+
+#[test]
+fn corrupted_trailer_is_a_mismatch() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect_with(checksummed()).unwrap();
+    client.put(1, b"a", b"1").unwrap();
+
+    server.corrupt_checksums(true);
+    let response = frame(STATUS_SUCCESS, b"1");
+    match client.get(1, b"a") {
+        Err(ClientError::ChecksumMismatch { expected, actual }) => {
+            assert_eq!(actual, crc32(&response));
+            assert_eq!(expected, crc32(&response) ^ 0x01);
+        }
+        other => panic!("expected ChecksumMismatch, got {:?}", other),
+    }
+    // The bad answer is not passed off as the next one either.
+    assert!(client.get(1, b"a").is_err_and(|e| e.is_disconnect()));
+
+    let mut client = server.connect_with(checksummed()).unwrap();
+    let mut value = Vec::new();
+    assert!(matches!(
+        client.get_writer(1, b"a", &mut value),
+        Err(ClientError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn corrupted_trailer_from_a_scripted_server() {
+    let mut answer = sealed(frame(STATUS_SUCCESS, b"value"));
+    *answer.last_mut().unwrap() ^= 0x80;
+    let server = ScriptedServer::start(vec![Step::Reply(frame(STATUS_SUCCESS, b"")), Step::Reply(answer)]);
+    let mut client = ScalerizeClient::connect_with(server.path(), checksummed()).unwrap();
+
+    assert!(matches!(client.get(1, b"k"), Err(ClientError::ChecksumMismatch { .. })));
+}
+
+#[test]
+fn reconnect_negotiates_checksums_again() {
+    let options = checksummed().reconnect(3, Duration::from_millis(10));
+
+    // On, then a restarted server without them.
+    let server = MockServer::start().unwrap();
+    let path = server.path().to_path_buf();
+    let mut client = server.connect_with(options.clone()).unwrap();
+    assert!(client.checksums_enabled());
+    drop(server);
+    let restarted = MockServer::start_at(&path).unwrap();
+    restarted.set_checksums(false);
+    client.put(1, b"a", b"1").unwrap();
+    assert!(!client.checksums_enabled());
+    assert_eq!(restarted.value(1, b"a"), Some(b"1".to_vec()));
+
+    // Off, then a restarted server that has them.
+    let server = MockServer::start().unwrap();
+    server.set_checksums(false);
+    let path = server.path().to_path_buf();
+    let mut client = server.connect_with(options).unwrap();
+    assert!(!client.checksums_enabled());
+    drop(server);
+    let restarted = MockServer::start_at(&path).unwrap();
+    client.put(1, b"a", b"1").unwrap();
+    assert!(client.checksums_enabled());
+    assert_eq!(restarted.value(1, b"a"), Some(b"1".to_vec()));
+    assert_eq!(client.get(1, b"a").unwrap(), Some(b"1".to_vec()));
+}
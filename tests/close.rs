@@ -0,0 +1,89 @@
+mod common;
+
+use std::os::unix::net::UnixListener;
+use std::time::{Duration, Instant};
+
+use common::{frame, socket_path, Hangup, ScriptedServer, Step};
+use scalerize_client::client::DEFAULT_CLOSE_TIMEOUT;
+use scalerize_client::protocol::{OP_GOODBYE, STATUS_SUCCESS};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ScalerizeClient};
+
+#[test]
+fn close_says_goodbye_and_the_server_sees_eof() {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(STATUS_SUCCESS, b""))]);
+    let client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    client.close().unwrap();
+
+    let transcript = server.finish();
+    assert_eq!(transcript.bytes(), [OP_GOODBYE, 0]);
+    assert_eq!(transcript.hangup, Hangup::Eof);
+}
+
+#[test]
+fn drop_leaves_the_server_an_eof() {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(STATUS_SUCCESS, b"v"))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+    client.get(1, b"k").unwrap();
+
+    drop(client);
+
+    assert_eq!(server.finish().hangup, Hangup::Eof);
+}
+
+// Closing a socket with unread input makes the kernel send a reset, so both
+// ways out must drain what the server pushed unasked.
+#[test]
+fn unread_bytes_are_drained_before_close_and_drop() {
+    let unasked = [frame(STATUS_SUCCESS, b"v"), b"unsolicited".to_vec()].concat();
+
+    let server = ScriptedServer::start(vec![Step::Reply(unasked.clone())]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+    client.get(1, b"k").unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    drop(client);
+    assert_eq!(server.finish().hangup, Hangup::Eof);
+
+    let server = ScriptedServer::start(vec![Step::Reply(unasked), Step::Reply(frame(STATUS_SUCCESS, b""))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+    client.get(1, b"k").unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    client.close().unwrap();
+    assert_eq!(server.finish().hangup, Hangup::Eof);
+}
+
+#[test]
+fn close_against_the_mock_is_clean() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    client.put(1, b"k", b"v").unwrap();
+    client.close().unwrap();
+}
+
+#[test]
+fn close_after_the_server_hung_up_is_ok() {
+    let server = ScriptedServer::start(vec![Step::ReplyAndClose(frame(STATUS_SUCCESS, b""))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+    client.put(1, b"k", b"v").unwrap();
+    server.finish();
+
+    // The goodbye hits a broken pipe.
+    client.close().unwrap();
+}
+
+#[test]
+fn close_gives_up_on_a_server_that_never_hangs_up() {
+    let path = socket_path("close");
+    let listener = UnixListener::bind(&path).unwrap();
+    let client = ScalerizeClient::connect_to(&path).unwrap();
+    let (_held, _) = listener.accept().unwrap();
+
+    let started = Instant::now();
+    let result = client.close();
+
+    assert!(matches!(result, Err(ClientError::Timeout)), "{:?}", result);
+    assert!(started.elapsed() >= DEFAULT_CLOSE_TIMEOUT);
+    assert!(started.elapsed() < DEFAULT_CLOSE_TIMEOUT * 2);
+    let _ = std::fs::remove_file(&path);
+}
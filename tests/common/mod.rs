@@ -0,0 +1,178 @@
+//! A scripted server that plays back canned responses, for the cases
+//! `MockServer` cannot produce: slow or silent servers, odd framing, old or
+//! misbehaving servers. It records every byte the client sends and how the
+//! client hung up.
+
+// Each test crate uses a different part of this.
+#![allow(dead_code)]
+
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+pub enum Step {
+    /// Reads one request, then writes the response in one go.
+    Reply(Vec<u8>),
+    /// Reads one request, waits, then writes the response.
+    ReplyAfter(Duration, Vec<u8>),
+    /// Reads one request, then writes the response `chunk` bytes at a time
+    /// with a pause between writes.
+    ReplyChunked(Vec<u8>, usize),
+    /// Reads one request, writes the response and closes the connection.
+    ReplyAndClose(Vec<u8>),
+}
+
+/// How the client's side of the connection ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hangup {
+    Eof,
+    Reset,
+    /// Still open when the server gave up waiting.
+    TimedOut,
+    /// The script closed the connection itself.
+    Closed,
+}
+
+pub struct Transcript {
+    /// One entry per read that returned data.
+    pub reads: Vec<Vec<u8>>,
+    pub hangup: Hangup,
+}
+
+impl Transcript {
+    pub fn bytes(&self) -> Vec<u8> {
+        self.reads.concat()
+    }
+}
+
+pub struct ScriptedServer {
+    path: PathBuf,
+    thread: Option<JoinHandle<Vec<Transcript>>>,
+}
+
+pub fn socket_path(name: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("scalerize-test-{}-{}-{}.sock", name, std::process::id(), n))
+}
+
+impl ScriptedServer {
+    pub fn start(steps: Vec<Step>) -> Self {
+        Self::start_at(socket_path("scripted"), steps)
+    }
+
+    pub fn start_at(path: PathBuf, steps: Vec<Step>) -> Self {
+        Self::start_sessions_at(path, vec![steps])
+    }
+
+    /// Accepts one connection per script, in order, and plays each on its
+    /// own thread, for clients that open a second connection themselves.
+    pub fn start_sessions(sessions: Vec<Vec<Step>>) -> Self {
+        Self::start_sessions_at(socket_path("scripted"), sessions)
+    }
+
+    fn start_sessions_at(path: PathBuf, sessions: Vec<Vec<Step>>) -> Self {
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).expect("bind scripted server");
+        let thread = std::thread::spawn(move || {
+            let players: Vec<_> = sessions
+                .into_iter()
+                .map(|steps| {
+                    let (stream, _) = listener.accept().expect("accept");
+                    std::thread::spawn(move || play(stream, steps))
+                })
+                .collect();
+            players
+                .into_iter()
+                .map(|player| player.join().expect("scripted session panicked"))
+                .collect()
+        });
+        Self {
+            path,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Waits for the client to hang up and returns what it sent.
+    pub fn finish(self) -> Transcript {
+        self.finish_sessions().remove(0)
+    }
+
+    /// Like `finish`, with one transcript per session.
+    pub fn finish_sessions(mut self) -> Vec<Transcript> {
+        self.thread.take().unwrap().join().expect("scripted server panicked")
+    }
+}
+
+fn play(mut stream: UnixStream, steps: Vec<Step>) -> Transcript {
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut reads = Vec::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    let mut read = |stream: &mut UnixStream, reads: &mut Vec<Vec<u8>>| -> Option<Hangup> {
+        match stream.read(&mut buffer) {
+            Ok(0) => Some(Hangup::Eof),
+            Ok(n) => {
+                reads.push(buffer[..n].to_vec());
+                None
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => Some(Hangup::Reset),
+            Err(_) => Some(Hangup::TimedOut),
+        }
+    };
+
+    for step in steps {
+        if let Some(hangup) = read(&mut stream, &mut reads) {
+            return Transcript { reads, hangup };
+        }
+        let written = match step {
+            Step::Reply(response) => stream.write_all(&response),
+            Step::ReplyAfter(delay, response) => {
+                std::thread::sleep(delay);
+                stream.write_all(&response)
+            }
+            Step::ReplyChunked(response, chunk) => response.chunks(chunk).try_for_each(|piece| {
+                std::thread::sleep(Duration::from_millis(1));
+                stream.write_all(piece)
+            }),
+            Step::ReplyAndClose(response) => {
+                let _ = stream.write_all(&response);
+                return Transcript {
+                    reads,
+                    hangup: Hangup::Closed,
+                };
+            }
+        };
+        if written.is_err() {
+            return Transcript {
+                reads,
+                hangup: Hangup::Reset,
+            };
+        }
+    }
+    loop {
+        if let Some(hangup) = read(&mut stream, &mut reads) {
+            return Transcript { reads, hangup };
+        }
+    }
+}
+
+impl Drop for ScriptedServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// A response frame: `[status][u32 len][payload]`.
+pub fn frame(status: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![status];
+    out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    out.extend_from_slice(payload);
+    out
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ClientOptions, ScalerizeClient};
+
+#[test]
+fn response_split_across_small_writes_is_reassembled() {
+    let value: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+    let server = ScriptedServer::start(vec![Step::ReplyChunked(frame(1, &value), 700)]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    assert_eq!(client.get(1, b"key").unwrap(), Some(value));
+}
+
+#[test]
+fn header_split_byte_by_byte_is_reassembled() {
+    let server = ScriptedServer::start(vec![
+        Step::ReplyChunked(frame(1, b"hello"), 1),
+        Step::ReplyChunked(frame(2, b""), 1),
+    ]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    assert_eq!(client.get(1, b"a").unwrap(), Some(b"hello".to_vec()));
+    assert_eq!(client.get(1, b"b").unwrap(), None);
+}
+
+#[test]
+fn payload_cut_short_by_the_server_is_an_error() {
+    let mut truncated = frame(1, &[7u8; 100]);
+    truncated.truncate(50);
+    let server = ScriptedServer::start(vec![Step::ReplyAndClose(truncated)]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    let err = client.get(1, b"key").unwrap_err();
+    assert!(err.is_disconnect(), "{:?}", err);
+}
+
+#[test]
+fn oversized_response_is_rejected_before_buffering() {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(1, &[0u8; 2048]))]);
+    let options = ClientOptions::new().max_response_size(1024);
+    let mut client = ScalerizeClient::connect_with(server.path(), options).unwrap();
+
+    assert!(matches!(client.get(1, b"key"), Err(ClientError::InvalidResponse(_))));
+}
+
+#[test]
+fn multi_megabyte_value_round_trips() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    let value: Vec<u8> = (0..8 * 1024 * 1024u32).map(|i| (i * 31 % 257) as u8).collect();
+
+    client.put(3, b"large", &value).unwrap();
+    assert_eq!(client.get(3, b"large").unwrap(), Some(value));
+}
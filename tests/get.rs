@@ -0,0 +1,44 @@
+mod common;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ScalerizeClient};
+
+#[test]
+fn found_key_returns_its_value() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    client.put(1, b"key", b"value").unwrap();
+
+    assert_eq!(client.get(1, b"key").unwrap(), Some(b"value".to_vec()));
+}
+
+#[test]
+fn missing_key_is_none() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    client.put(1, b"key", b"value").unwrap();
+
+    assert_eq!(client.get(1, b"other").unwrap(), None);
+    // Same key, different store.
+    assert_eq!(client.get(2, b"key").unwrap(), None);
+}
+
+#[test]
+fn server_error_is_operation_failed_with_the_raw_message() {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(0, b"corrupt store \xff"))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    match client.get(1, b"key") {
+        Err(ClientError::OperationFailed(message)) => assert_eq!(message, b"corrupt store \xff"),
+        other => panic!("expected OperationFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn unknown_status_is_an_invalid_response() {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(9, b""))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    assert!(matches!(client.get(1, b"key"), Err(ClientError::InvalidResponse(_))));
+}
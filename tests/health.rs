@@ -0,0 +1,107 @@
+mod common;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::protocol::{
+    OP_PING, OP_SERVER_INFO, PROTOCOL_VERSION, STATUS_ERROR, STATUS_SUCCESS, STATUS_UNSUPPORTED,
+};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ClientOptions, ScalerizeClient, ServerInfo};
+
+fn server_info(protocol_version: u16, version: &[u8]) -> Vec<u8> {
+    let mut payload = protocol_version.to_be_bytes().to_vec();
+    payload.extend_from_slice(&(version.len() as u32).to_be_bytes());
+    payload.extend_from_slice(version);
+    frame(STATUS_SUCCESS, &payload)
+}
+
+#[test]
+fn ping_sends_opcode_and_store_zero() {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(STATUS_SUCCESS, b""))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    client.ping().unwrap();
+    drop(client);
+    assert_eq!(server.finish().bytes(), [OP_PING, 0]);
+}
+
+#[test]
+fn server_info_decodes_version_and_string() {
+    let server = ScriptedServer::start(vec![Step::Reply(server_info(0x0103, b"scalerize 1.3.0"))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    assert_eq!(
+        client.server_info().unwrap(),
+        ServerInfo {
+            protocol_version: 0x0103,
+            version: "scalerize 1.3.0".to_string(),
+        }
+    );
+    drop(client);
+    assert_eq!(server.finish().bytes(), [OP_SERVER_INFO, 0]);
+}
+
+#[test]
+fn truncated_server_info_is_invalid() {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(STATUS_SUCCESS, &[0x01]))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    assert!(matches!(client.server_info(), Err(ClientError::InvalidResponse(_))));
+}
+
+#[test]
+fn handshake_accepts_a_newer_minor_version() {
+    let server = ScriptedServer::start(vec![Step::Reply(server_info(PROTOCOL_VERSION + 7, b"newer"))]);
+    let options = ClientOptions::new().handshake(true);
+
+    ScalerizeClient::connect_with(server.path(), options).unwrap();
+}
+
+#[test]
+fn handshake_rejects_another_major_version() {
+    let server = ScriptedServer::start(vec![Step::Reply(server_info(0x0200, b"2.0"))]);
+    let options = ClientOptions::new().handshake(true);
+
+    match ScalerizeClient::connect_with(server.path(), options) {
+        Err(ClientError::IncompatibleProtocol { client, server }) => {
+            assert_eq!(client, PROTOCOL_VERSION);
+            assert_eq!(server, 0x0200);
+        }
+        other => panic!("expected IncompatibleProtocol, got {:?}", other.map(|_| ())),
+    }
+}
+
+// Servers that predate the probes answer with an error, STATUS_UNSUPPORTED
+// or a status byte this client does not know.
+#[test]
+fn old_servers_are_unsupported() {
+    for status in [STATUS_ERROR, STATUS_UNSUPPORTED, 0x7f] {
+        let server = ScriptedServer::start(vec![
+            Step::Reply(frame(status, b"unknown opcode")),
+            Step::Reply(frame(status, b"unknown opcode")),
+        ]);
+        let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+        assert!(matches!(client.ping(), Err(ClientError::Unsupported("ping"))), "status {}", status);
+        assert!(
+            matches!(client.server_info(), Err(ClientError::Unsupported("server_info"))),
+            "status {}",
+            status
+        );
+    }
+
+    let server = ScriptedServer::start(vec![Step::Reply(frame(STATUS_ERROR, b"unknown opcode"))]);
+    let options = ClientOptions::new().handshake(true);
+    assert!(matches!(
+        ScalerizeClient::connect_with(server.path(), options),
+        Err(ClientError::Unsupported("server_info"))
+    ));
+}
+
+#[test]
+fn mock_passes_the_handshake() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect_with(ClientOptions::new().handshake(true)).unwrap();
+
+    assert_eq!(client.server_info().unwrap().protocol_version, PROTOCOL_VERSION);
+    client.ping().unwrap();
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::ScriptedServer;
+use scalerize_client::client::DEFAULT_MAX_KEY_SIZE;
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ClientOptions, ScalerizeClient};
+
+const MAX_KEY: usize = 8;
+const MAX_VALUE: usize = 16;
+const MAX_STORE: u8 = 3;
+
+fn limited() -> ClientOptions {
+    ClientOptions::new()
+        .max_key_size(MAX_KEY)
+        .max_value_size(MAX_VALUE)
+        .max_store_number(MAX_STORE)
+}
+
+fn rejected<T: std::fmt::Debug>(result: Result<T, ClientError>) -> String {
+    match result {
+        Err(ClientError::InvalidArgument(message)) => message,
+        other => panic!("expected InvalidArgument, got {:?}", other),
+    }
+}
+
+#[test]
+fn key_limit_is_inclusive() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect_with(limited()).unwrap();
+
+    client.put(1, &[b'k'; MAX_KEY], b"v").unwrap();
+    assert_eq!(
+        rejected(client.put(1, &[b'k'; MAX_KEY + 1], b"v")),
+        "key of 9 bytes exceeds the 8 byte limit"
+    );
+    assert_eq!(rejected(client.get(1, b"")), "key is empty");
+    assert_eq!(client.get(1, &[b'k'; MAX_KEY]).unwrap(), Some(b"v".to_vec()));
+    rejected(client.get(1, &[b'k'; MAX_KEY + 1]));
+    rejected(client.delete(1, &[b'k'; MAX_KEY + 1]));
+    client.scan(1, Some(&[b'k'; MAX_KEY])).unwrap();
+    rejected(client.scan(1, Some(&[b'k'; MAX_KEY + 1])));
+}
+
+#[test]
+fn default_key_limit_is_inclusive() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+
+    assert_eq!(client.get(1, &vec![b'k'; DEFAULT_MAX_KEY_SIZE]).unwrap(), None);
+    rejected(client.get(1, &vec![b'k'; DEFAULT_MAX_KEY_SIZE + 1]));
+}
+
+#[test]
+fn value_limit_is_inclusive() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect_with(limited()).unwrap();
+
+    client.put(1, b"k", &[b'v'; MAX_VALUE]).unwrap();
+    assert_eq!(
+        rejected(client.put(1, b"k", &[b'v'; MAX_VALUE + 1])),
+        "value of 17 bytes exceeds the 16 byte limit"
+    );
+    let ok: &[u8] = &[b'v'; MAX_VALUE];
+    let over: &[u8] = &[b'v'; MAX_VALUE + 1];
+    assert_eq!(
+        rejected(client.put_many(1, &[(b"a", ok), (b"b", over)])),
+        "entry 1: value of 17 bytes exceeds the 16 byte limit"
+    );
+    rejected(client.put_if(1, b"k", Some(over), b"v"));
+    assert!(client.put_if(1, b"k", Some(ok), b"v").unwrap());
+}
+
+#[test]
+fn store_limit_is_inclusive() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect_with(limited()).unwrap();
+
+    client.put(MAX_STORE, b"k", b"v").unwrap();
+    assert_eq!(
+        rejected(client.put(MAX_STORE + 1, b"k", b"v")),
+        "store 4 is above the highest store number 3"
+    );
+    rejected(client.write(MAX_STORE + 1));
+    client.write(MAX_STORE).unwrap();
+}
+
+#[test]
+fn put_reader_checks_the_declared_length() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect_with(limited()).unwrap();
+
+    client
+        .put_reader(1, b"k", MAX_VALUE as u64, Cursor::new(vec![b'v'; MAX_VALUE]))
+        .unwrap();
+    assert_eq!(server.value(1, b"k"), Some(vec![b'v'; MAX_VALUE]));
+
+    // Refused on the length alone, before the reader is touched.
+    assert_eq!(
+        rejected(client.put_reader(1, b"k", MAX_VALUE as u64 + 1, Cursor::new(Vec::new()))),
+        "value of 17 bytes exceeds the 16 byte limit"
+    );
+    rejected(client.put_reader(1, &[b'k'; MAX_KEY + 1], 1, Cursor::new(vec![b'v'])));
+    rejected(client.put_reader(MAX_STORE + 1, b"k", 1, Cursor::new(vec![b'v'])));
+
+    // The connection is still usable after the rejections.
+    assert_eq!(client.get(1, b"k").unwrap(), Some(vec![b'v'; MAX_VALUE]));
+}
+
+#[test]
+fn rejected_requests_never_reach_the_socket() {
+    let server = ScriptedServer::start(vec![]);
+    let mut client = ScalerizeClient::connect_with(server.path(), limited()).unwrap();
+
+    rejected(client.put(1, &[b'k'; MAX_KEY + 1], b"v"));
+    rejected(client.put(1, b"k", &[b'v'; MAX_VALUE + 1]));
+    rejected(client.get(MAX_STORE + 1, b"k"));
+    rejected(client.put_reader(1, b"k", MAX_VALUE as u64 + 1, Cursor::new(Vec::new())));
+    drop(client);
+
+    assert!(server.finish().reads.is_empty());
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::protocol::{Request, Response, Status, STATUS_ERROR, STATUS_NOT_FOUND, STATUS_SUCCESS};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ClientOptions, OperationMeta, ScalerizeClient};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Recorded {
+    op: &'static str,
+    status: Option<u8>,
+    request_len: usize,
+    response_len: usize,
+    failed: bool,
+}
+
+fn recording() -> (ClientOptions, Arc<Mutex<Vec<Recorded>>>) {
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let sink = {
+        let recorded = Arc::clone(&recorded);
+        move |meta: &OperationMeta<'_>| {
+            recorded.lock().unwrap().push(Recorded {
+                op: meta.op,
+                status: meta.status,
+                request_len: meta.request_len,
+                response_len: meta.response_len,
+                failed: meta.error.is_some(),
+            })
+        }
+    };
+    (ClientOptions::new().metrics(Arc::new(sink)), recorded)
+}
+
+fn request_len(request: Request<'_>) -> usize {
+    request.encode().unwrap().len()
+}
+
+fn response_len(status: Status, payload: &[u8]) -> usize {
+    Response { status, payload }.encode().unwrap().len()
+}
+
+#[test]
+fn lengths_match_the_encoded_frames() {
+    let server = MockServer::start().unwrap();
+    let (options, recorded) = recording();
+    let mut client = server.connect_with(options).unwrap();
+
+    client.put(1, b"key", b"value").unwrap();
+    client.get(1, b"key").unwrap();
+    client.get(1, b"missing").unwrap();
+    client.delete(1, b"key").unwrap();
+
+    assert_eq!(
+        *recorded.lock().unwrap(),
+        [
+            Recorded {
+                op: "put",
+                status: Some(STATUS_SUCCESS),
+                request_len: request_len(Request::Put {
+                    store: 1,
+                    key: b"key",
+                    value: b"value"
+                }),
+                response_len: response_len(Status::Success, b""),
+                failed: false,
+            },
+            Recorded {
+                op: "get",
+                status: Some(STATUS_SUCCESS),
+                request_len: request_len(Request::Get { store: 1, key: b"key" }),
+                response_len: response_len(Status::Success, b"value"),
+                failed: false,
+            },
+            Recorded {
+                op: "get",
+                status: Some(STATUS_NOT_FOUND),
+                request_len: request_len(Request::Get { store: 1, key: b"missing" }),
+                response_len: response_len(Status::NotFound, b""),
+                failed: false,
+            },
+            Recorded {
+                op: "delete",
+                status: Some(STATUS_SUCCESS),
+                request_len: request_len(Request::Delete { store: 1, key: b"key" }),
+                response_len: response_len(Status::Success, b""),
+                failed: false,
+            },
+        ]
+    );
+}
+
+#[test]
+fn pipeline_reports_the_whole_batch_once() {
+    let server = MockServer::start().unwrap();
+    let (options, recorded) = recording();
+    let mut client = server.connect_with(options).unwrap();
+    client.put(1, b"a", b"1").unwrap();
+    recorded.lock().unwrap().clear();
+
+    let mut pipeline = client.pipeline();
+    pipeline.get(1, b"a").get(1, b"b");
+    pipeline.flush().unwrap();
+
+    let mut framed = Vec::new();
+    Request::Get { store: 1, key: b"a" }.encode_framed(&mut framed).unwrap();
+    Request::Get { store: 1, key: b"b" }.encode_framed(&mut framed).unwrap();
+    assert_eq!(
+        *recorded.lock().unwrap(),
+        [Recorded {
+            op: "pipeline",
+            status: None,
+            request_len: framed.len(),
+            response_len: response_len(Status::Success, b"1") + response_len(Status::NotFound, b""),
+            failed: false,
+        }]
+    );
+}
+
+#[test]
+fn server_error_shows_in_status_and_exchange_failure_in_error() {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(STATUS_ERROR, b"disk full"))]);
+    let (options, recorded) = recording();
+    let mut client =
+        ScalerizeClient::connect_with(server.path(), options.read_timeout(Some(Duration::from_millis(50)))).unwrap();
+
+    assert!(matches!(client.put(1, b"k", b"v"), Err(ClientError::OperationFailed(_))));
+    // The script is done; nothing answers this one.
+    assert!(matches!(client.get(1, b"k"), Err(ClientError::Timeout)));
+
+    assert_eq!(
+        *recorded.lock().unwrap(),
+        [
+            Recorded {
+                op: "put",
+                status: Some(STATUS_ERROR),
+                request_len: request_len(Request::Put {
+                    store: 1,
+                    key: b"k",
+                    value: b"v"
+                }),
+                response_len: response_len(Status::Error, b"disk full"),
+                failed: false,
+            },
+            Recorded {
+                op: "get",
+                status: None,
+                request_len: request_len(Request::Get { store: 1, key: b"k" }),
+                response_len: 0,
+                failed: true,
+            },
+        ]
+    );
+}
+
+#[test]
+fn requests_rejected_before_sending_are_not_reported() {
+    let server = MockServer::start().unwrap();
+    let (options, recorded) = recording();
+    let mut client = server.connect_with(options).unwrap();
+
+    assert!(matches!(client.get(1, b""), Err(ClientError::InvalidArgument(_))));
+    assert!(recorded.lock().unwrap().is_empty());
+}
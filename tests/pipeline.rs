@@ -0,0 +1,74 @@
+mod common;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::protocol::{STATUS_ERROR, STATUS_NOT_FOUND, STATUS_SUCCESS};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, Reply, ScalerizeClient};
+
+#[test]
+fn results_line_up_with_the_queue() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+
+    let mut pipeline = client.pipeline();
+    pipeline.put(1, b"a", b"1").get(1, b"missing").delete(1, b"a").get(1, b"a");
+    let replies = pipeline.flush().unwrap();
+
+    assert_eq!(replies.len(), 4);
+    assert_eq!(replies[0].as_ref().unwrap(), &Reply::Done);
+    assert_eq!(replies[1].as_ref().unwrap(), &Reply::Value(None));
+    assert_eq!(replies[2].as_ref().unwrap(), &Reply::Done);
+    assert_eq!(replies[3].as_ref().unwrap(), &Reply::Value(None));
+}
+
+#[test]
+fn rejected_request_keeps_its_slot_and_the_next_one_succeeds() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+
+    let mut pipeline = client.pipeline();
+    pipeline.put(1, b"a", b"1").get(1, b"").get(1, b"a");
+    let replies = pipeline.flush().unwrap();
+
+    assert_eq!(replies[0].as_ref().unwrap(), &Reply::Done);
+    assert!(matches!(replies[1], Err(ClientError::InvalidArgument(_))));
+    assert_eq!(replies[2].as_ref().unwrap(), &Reply::Value(Some(b"1".to_vec())));
+}
+
+#[test]
+fn server_error_fails_only_its_own_slot() {
+    let answers = [
+        frame(STATUS_SUCCESS, b""),
+        frame(STATUS_ERROR, b"store is read-only"),
+        frame(STATUS_SUCCESS, b"1"),
+        frame(STATUS_NOT_FOUND, b""),
+    ]
+    .concat();
+    let server = ScriptedServer::start(vec![Step::Reply(answers), Step::Reply(frame(STATUS_SUCCESS, b""))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    let mut pipeline = client.pipeline();
+    pipeline.put(1, b"a", b"1").put(1, b"b", b"2").get(1, b"a").get(1, b"b");
+    let replies = pipeline.flush().unwrap();
+
+    assert_eq!(replies[0].as_ref().unwrap(), &Reply::Done);
+    match &replies[1] {
+        Err(ClientError::OperationFailed(message)) => assert_eq!(message, b"store is read-only"),
+        other => panic!("expected OperationFailed, got {:?}", other),
+    }
+    assert_eq!(replies[2].as_ref().unwrap(), &Reply::Value(Some(b"1".to_vec())));
+    assert_eq!(replies[3].as_ref().unwrap(), &Reply::Value(None));
+
+    // Every answer was consumed, so the connection is still in step.
+    client.put(1, b"c", b"3").unwrap();
+}
+
+#[test]
+fn empty_flush_sends_nothing() {
+    let server = ScriptedServer::start(vec![]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    assert!(client.pipeline().flush().unwrap().is_empty());
+    drop(client);
+    assert!(server.finish().reads.is_empty());
+}
@@ -0,0 +1,126 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ClientOptions, ScalerizeClient, ScalerizePool};
+
+#[test]
+fn eight_threads_share_three_connections() {
+    let server = MockServer::start().unwrap();
+    let pool = ScalerizePool::for_path(server.path(), 3);
+
+    let (done, finished) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for thread in 0..8u8 {
+            let pool = &pool;
+            let done = done.clone();
+            scope.spawn(move || {
+                for i in 0..200u32 {
+                    let key = format!("t{}-{}", thread, i);
+                    let value = format!("value {}", i);
+                    let mut client = pool.get().unwrap();
+                    client.put(1, key.as_bytes(), value.as_bytes()).unwrap();
+                    assert_eq!(client.get(1, key.as_bytes()).unwrap(), Some(value.into_bytes()));
+                    // Every few rounds, go around the wrappers.
+                    if i % 7 == 0 {
+                        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+                        let mut pipeline = client.pipeline();
+                        pipeline.get(1, key.as_bytes());
+                        pipeline.flush().unwrap();
+                    }
+                }
+                done.send(thread).unwrap();
+            });
+        }
+        drop(done);
+
+        for _ in 0..8 {
+            finished
+                .recv_timeout(Duration::from_secs(30))
+                .expect("pool deadlocked");
+        }
+    });
+
+    for thread in 0..8u8 {
+        let key = format!("t{}-199", thread);
+        assert_eq!(server.value(1, key.as_bytes()), Some(b"value 199".to_vec()));
+    }
+}
+
+#[test]
+fn a_stream_left_mid_response_is_not_checked_in() {
+    let server = MockServer::start().unwrap();
+    let pool = ScalerizePool::for_path(server.path(), 1);
+    pool.get().unwrap().put(1, b"key", b"ten bytes!").unwrap();
+
+    {
+        let mut guard = pool.get().unwrap();
+        // Straight to the client, so the guard never sees the error.
+        let client: &mut ScalerizeClient = &mut guard;
+        client.set_max_response_size(4);
+        assert!(matches!(client.get(1, b"key"), Err(ClientError::InvalidResponse(_))));
+    }
+
+    // The rejected payload was never read; reusing that stream would return
+    // it as the next response.
+    let mut client = pool.get().unwrap();
+    assert_eq!(client.get(1, b"key").unwrap(), Some(b"ten bytes!".to_vec()));
+}
+
+#[test]
+fn pool_options_survive_a_guard_that_changed_them() {
+    let server = MockServer::start().unwrap();
+    let mut pool = ScalerizePool::for_path(server.path(), 1);
+    pool.set_client_options(ClientOptions::new().max_response_size(16));
+    pool.get().unwrap().put(1, b"big", &[7; 32]).unwrap();
+
+    pool.get().unwrap().set_max_response_size(usize::MAX);
+
+    let mut client = pool.get().unwrap();
+    assert!(matches!(client.get(1, b"big"), Err(ClientError::InvalidResponse(_))));
+}
+
+#[test]
+fn put_if_inserts_only_when_absent() {
+    let server = MockServer::start().unwrap();
+    let pool = ScalerizePool::for_path(server.path(), 1);
+    let mut client = pool.get().unwrap();
+
+    assert!(client.put_if(1, b"key", None, b"first").unwrap());
+    assert!(!client.put_if(1, b"key", None, b"second").unwrap());
+    assert_eq!(server.value(1, b"key"), Some(b"first".to_vec()));
+}
+
+#[test]
+fn put_if_swaps_only_the_expected_value() {
+    let server = MockServer::start().unwrap();
+    let pool = ScalerizePool::for_path(server.path(), 1);
+    let mut client = pool.get().unwrap();
+    client.put(1, b"key", b"old").unwrap();
+
+    assert!(client.put_if(1, b"key", Some(b"old"), b"new").unwrap());
+    assert_eq!(server.value(1, b"key"), Some(b"new".to_vec()));
+
+    assert!(!client.put_if(1, b"key", Some(b"old"), b"newer").unwrap());
+    assert_eq!(server.value(1, b"key"), Some(b"new".to_vec()));
+}
+
+#[test]
+fn put_if_is_not_retried_after_a_disconnect() {
+    let server = MockServer::start().unwrap();
+    let path = server.path().to_path_buf();
+    let pool = ScalerizePool::for_path(&path, 1);
+    let mut client = pool.get().unwrap();
+    client.ping().unwrap();
+
+    drop(server);
+    let restarted = MockServer::start_at(&path).unwrap();
+
+    let result = client.put_if(1, b"key", None, b"value");
+    assert!(result.as_ref().is_err_and(ClientError::is_disconnect), "{:?}", result);
+    assert_eq!(restarted.value(1, b"key"), None);
+    drop(client);
+
+    // The dead connection was not checked back in.
+    assert!(pool.get().unwrap().put_if(1, b"key", None, b"value").unwrap());
+}
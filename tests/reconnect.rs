@@ -0,0 +1,86 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::protocol::{Request, PROTOCOL_VERSION};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ClientOptions};
+
+fn reconnecting() -> ClientOptions {
+    ClientOptions::new()
+        .read_timeout(Some(Duration::from_secs(5)))
+        .reconnect(3, Duration::from_millis(10))
+}
+
+fn server_info(protocol_version: u16) -> Vec<u8> {
+    let mut payload = protocol_version.to_be_bytes().to_vec();
+    payload.extend_from_slice(&7u32.to_be_bytes());
+    payload.extend_from_slice(b"restart");
+    frame(1, &payload)
+}
+
+#[test]
+fn get_after_a_server_restart_reconnects() {
+    let server = MockServer::start().unwrap();
+    let path = server.path().to_path_buf();
+    let mut client = server.connect_with(reconnecting()).unwrap();
+    client.put(1, b"key", b"before").unwrap();
+
+    drop(server);
+    let restarted = MockServer::start_at(&path).unwrap();
+    restarted.connect().unwrap().put(1, b"key", b"after").unwrap();
+
+    assert_eq!(client.get(1, b"key").unwrap(), Some(b"after".to_vec()));
+    // And the new connection is kept for the next request.
+    client.put(1, b"other", b"value").unwrap();
+    assert_eq!(restarted.value(1, b"other"), Some(b"value".to_vec()));
+}
+
+#[test]
+fn no_server_to_come_back_to_is_reconnect_failed() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect_with(reconnecting()).unwrap();
+    drop(server);
+
+    match client.get(1, b"key") {
+        Err(ClientError::ReconnectFailed { attempts, .. }) => assert_eq!(attempts, 3),
+        other => panic!("expected ReconnectFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn reconnect_repeats_the_handshake() {
+    let server = MockServer::start().unwrap();
+    let path = server.path().to_path_buf();
+    let mut client = server.connect_with(reconnecting().handshake(true)).unwrap();
+
+    drop(server);
+    let restarted = ScriptedServer::start_at(
+        path,
+        vec![Step::Reply(server_info(PROTOCOL_VERSION)), Step::Reply(frame(1, b"value"))],
+    );
+
+    assert_eq!(client.get(1, b"key").unwrap(), Some(b"value".to_vec()));
+    drop(client);
+
+    let transcript = restarted.finish();
+    let mut expected = Request::ServerInfo.encode().unwrap();
+    expected.extend(Request::Get { store: 1, key: b"key" }.encode().unwrap());
+    assert_eq!(transcript.bytes(), expected);
+}
+
+#[test]
+fn reconnect_to_an_incompatible_server_fails_the_handshake() {
+    let server = MockServer::start().unwrap();
+    let path = server.path().to_path_buf();
+    let mut client = server.connect_with(reconnecting().handshake(true)).unwrap();
+
+    drop(server);
+    let _restarted = ScriptedServer::start_at(path, vec![Step::Reply(server_info(0x0200))]);
+
+    assert!(matches!(
+        client.get(1, b"key"),
+        Err(ClientError::IncompatibleProtocol { server: 0x0200, .. })
+    ));
+}
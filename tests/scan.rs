@@ -0,0 +1,77 @@
+mod common;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ClientOptions, ScalerizeClient};
+
+const ENTRIES: usize = 10_000;
+
+fn filled_server() -> MockServer {
+    let server = MockServer::start().unwrap();
+    let keys: Vec<String> = (0..ENTRIES).map(|i| format!("key-{:05}", i)).collect();
+    let values: Vec<String> = (0..ENTRIES).map(|i| format!("value {}", i)).collect();
+    let pairs: Vec<(&[u8], &[u8])> = keys.iter().zip(&values).map(|(k, v)| (k.as_bytes(), v.as_bytes())).collect();
+    server.connect().unwrap().put_many(1, &pairs).unwrap();
+    server
+}
+
+#[test]
+fn scan_returns_ten_thousand_entries_in_key_order() {
+    let server = filled_server();
+    let mut client = server.connect().unwrap();
+
+    let entries = client.scan(1, None).unwrap();
+    assert_eq!(entries.len(), ENTRIES);
+    for (i, (key, value)) in entries.iter().enumerate() {
+        assert_eq!(key, format!("key-{:05}", i).as_bytes());
+        assert_eq!(value, format!("value {}", i).as_bytes());
+    }
+
+    let keys = client.scan_keys(1, None).unwrap();
+    assert_eq!(keys, entries.into_iter().map(|(key, _)| key).collect::<Vec<_>>());
+}
+
+#[test]
+fn scan_with_a_prefix_returns_only_matching_keys() {
+    let server = filled_server();
+    let mut client = server.connect().unwrap();
+
+    let entries = client.scan(1, Some(b"key-012")).unwrap();
+    assert_eq!(entries.len(), 100);
+    assert_eq!(entries[0], (b"key-01200".to_vec(), b"value 1200".to_vec()));
+    assert_eq!(client.scan_keys(1, Some(b"key-0129")).unwrap().len(), 10);
+    assert!(client.scan(1, Some(b"nope")).unwrap().is_empty());
+}
+
+#[test]
+fn empty_store_scans_to_nothing() {
+    let server = filled_server();
+    let mut client = server.connect().unwrap();
+
+    assert_eq!(client.scan(2, None).unwrap(), Vec::new());
+    assert_eq!(client.scan_keys(2, None).unwrap(), Vec::<Vec<u8>>::new());
+}
+
+#[test]
+fn empty_payload_and_zero_count_both_mean_no_entries() {
+    let server = ScriptedServer::start(vec![
+        Step::Reply(frame(1, b"")),
+        Step::Reply(frame(1, &0u32.to_be_bytes())),
+        Step::Reply(frame(1, b"")),
+    ]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    assert!(client.scan(1, None).unwrap().is_empty());
+    assert!(client.scan(1, None).unwrap().is_empty());
+    assert!(client.scan_keys(1, None).unwrap().is_empty());
+}
+
+#[test]
+fn scan_larger_than_max_response_size_is_refused() {
+    let server = filled_server();
+    let mut client = server
+        .connect_with(ClientOptions::new().max_response_size(64 * 1024))
+        .unwrap();
+
+    assert!(matches!(client.scan(1, None), Err(ClientError::InvalidResponse(_))));
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::protocol::{Request, STATUS_SUCCESS, STATUS_UNSUPPORTED};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ScalerizeClient};
+
+#[test]
+fn handle_sends_its_store_on_every_op() {
+    let ok = || Step::Reply(frame(STATUS_SUCCESS, b""));
+    let server = ScriptedServer::start(vec![ok(), ok(), ok(), ok()]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    let mut store = client.store(7);
+    assert_eq!(store.store_number(), 7);
+    store.put(b"k", b"v").unwrap();
+    store.get(b"k").unwrap();
+    store.delete(b"k").unwrap();
+    store.write().unwrap();
+    drop(client);
+
+    let expected: Vec<u8> = [
+        Request::Put { store: 7, key: b"k", value: b"v" },
+        Request::Get { store: 7, key: b"k" },
+        Request::Delete { store: 7, key: b"k" },
+        Request::Write { store: 7 },
+    ]
+    .iter()
+    .flat_map(|request| request.encode().unwrap())
+    .collect();
+    assert_eq!(server.finish().bytes(), expected);
+}
+
+#[test]
+fn sequential_handles_share_nothing() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+
+    client.store(1).put(b"k", b"one").unwrap();
+    assert_eq!(client.store(2).get(b"k").unwrap(), None);
+    client.store(2).put(b"k", b"two").unwrap();
+
+    assert_eq!(client.store(1).get(b"k").unwrap(), Some(b"one".to_vec()));
+    assert_eq!(client.store(2).get(b"k").unwrap(), Some(b"two".to_vec()));
+    client.store(1).delete(b"k").unwrap();
+    assert_eq!(server.value(1, b"k"), None);
+    assert_eq!(server.value(2, b"k"), Some(b"two".to_vec()));
+}
+
+#[test]
+fn list_stores_decodes_the_store_numbers() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    assert_eq!(client.list_stores().unwrap(), Vec::<u8>::new());
+
+    for store in [200, 1, 3] {
+        client.store(store).put(b"k", b"v").unwrap();
+    }
+    assert_eq!(client.list_stores().unwrap(), [1, 3, 200]);
+}
+
+#[test]
+fn list_stores_from_a_scripted_server() {
+    let server = ScriptedServer::start(vec![
+        Step::Reply(frame(STATUS_SUCCESS, &[0, 0, 0, 2, 1, 7])),
+        Step::Reply(frame(STATUS_SUCCESS, b"")),
+        Step::Reply(frame(STATUS_SUCCESS, &[0, 0, 0, 3, 1])),
+        Step::Reply(frame(STATUS_UNSUPPORTED, b"")),
+    ]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    assert_eq!(client.list_stores().unwrap(), [1, 7]);
+    assert_eq!(client.list_stores().unwrap(), Vec::<u8>::new());
+    assert!(matches!(client.list_stores(), Err(ClientError::InvalidResponse(_))));
+    // The whole frame was read, so the connection is still in step.
+    assert!(matches!(client.list_stores(), Err(ClientError::Unsupported("list_stores"))));
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::{Hangup, ScriptedServer};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ScalerizeClient};
+
+#[test]
+fn put_reader_streams_the_whole_value() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    let value: Vec<u8> = (0..300_000u32).map(|i| i as u8).collect();
+
+    client.put_reader(1, b"big", value.len() as u64, &value[..]).unwrap();
+    assert_eq!(server.value(1, b"big"), Some(value.clone()));
+
+    let mut out = Vec::new();
+    assert_eq!(client.get_writer(1, b"big", &mut out).unwrap(), Some(value.len() as u64));
+    assert_eq!(out, value);
+    assert_eq!(client.get_writer(1, b"missing", &mut out).unwrap(), None);
+}
+
+#[test]
+fn short_reader_aborts_before_the_frame_is_complete() {
+    let server = ScriptedServer::start(Vec::new());
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    let err = client.put_reader(1, b"key", 100_000, &[7u8; 1000][..]).unwrap_err();
+    assert!(matches!(err, ClientError::InvalidArgument(_)), "{:?}", err);
+    // The connection is shut down rather than reused.
+    assert!(client.put(1, b"key", b"v").is_err());
+    drop(client);
+
+    let transcript = server.finish();
+    let sent = transcript.bytes();
+    // [op][store]["key"][u32 value_len] then only the 1000 bytes the reader had.
+    assert_eq!(sent.len(), 2 + 3 + 4 + 1000);
+    assert_eq!(&sent[..9], &[1, 1, b'k', b'e', b'y', 0x00, 0x01, 0x86, 0xa0]);
+    assert_eq!(transcript.hangup, Hangup::Eof);
+}
+
+#[test]
+fn short_reader_leaves_the_mock_without_a_value() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    assert!(client.put_reader(1, b"key", 10, &b"abc"[..]).is_err());
+    drop(client);
+
+    let mut other = server.connect().unwrap();
+    assert_eq!(other.get(1, b"key").unwrap(), None);
+    assert_eq!(server.value(1, b"key"), None);
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::protocol::{OP_SUBSCRIBE, STATUS_SUCCESS};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ClientOptions, Event, EventKind, ScalerizeClient};
+
+fn event(kind: EventKind, store: u8, key: &[u8]) -> Event {
+    Event {
+        kind,
+        store,
+        key: key.to_vec(),
+    }
+}
+
+#[test]
+fn three_events_then_the_server_closes() {
+    let events = [
+        event(EventKind::Put, 1, b"a"),
+        event(EventKind::Delete, 1, b"a"),
+        event(EventKind::Write, 1, b""),
+    ];
+    let mut stream = frame(STATUS_SUCCESS, b"");
+    for event in &events {
+        stream.extend(event.encode().unwrap());
+    }
+    // The subscription gets a connection of its own.
+    let server = ScriptedServer::start_sessions(vec![vec![], vec![Step::ReplyAndClose(stream)]]);
+    let client = ScalerizeClient::connect_to(server.path()).unwrap();
+    let mut subscription = client.subscribe(1).unwrap();
+    drop(client);
+    let received: Vec<Event> = subscription.by_ref().map(Result::unwrap).collect();
+    assert_eq!(received, events);
+    assert!(subscription.next().is_none());
+    assert!(subscription.next_event().unwrap().is_none());
+
+    let sessions = server.finish_sessions();
+    assert!(sessions[0].reads.is_empty());
+    assert_eq!(sessions[1].bytes(), [OP_SUBSCRIBE, 1]);
+}
+
+#[test]
+fn mock_delivers_changes_to_the_subscribed_store_until_it_stops() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    let subscription = client.subscribe(1).unwrap();
+
+    client.put(1, b"a", b"1").unwrap();
+    client.put(2, b"elsewhere", b"1").unwrap();
+    client.delete(1, b"a").unwrap();
+    client.write(1).unwrap();
+    drop(server);
+
+    let received: Vec<Event> = subscription.map(Result::unwrap).collect();
+    assert_eq!(
+        received,
+        [
+            event(EventKind::Put, 1, b"a"),
+            event(EventKind::Delete, 1, b"a"),
+            event(EventKind::Write, 1, b""),
+        ]
+    );
+}
+
+#[test]
+fn event_cut_off_mid_frame_is_an_error_and_ends_the_subscription() {
+    let mut stream = frame(STATUS_SUCCESS, b"");
+    let whole = event(EventKind::Put, 1, b"key").encode().unwrap();
+    stream.extend(&whole[..whole.len() - 1]);
+    // The subscription gets a connection of its own.
+    let server = ScriptedServer::start_sessions(vec![vec![], vec![Step::ReplyAndClose(stream)]]);
+    let client = ScalerizeClient::connect_to(server.path()).unwrap();
+    let mut subscription = client.subscribe(1).unwrap();
+    drop(client);
+    assert!(matches!(subscription.next(), Some(Err(ClientError::Io(_)))));
+    assert!(subscription.next().is_none());
+}
+
+#[test]
+fn idle_subscription_times_out_and_stays_usable() {
+    let server = MockServer::start().unwrap();
+    let mut client = server
+        .connect_with(ClientOptions::new().read_timeout(Some(Duration::from_millis(50))))
+        .unwrap();
+    let mut subscription = client.subscribe(1).unwrap();
+
+    assert!(matches!(subscription.next(), Some(Err(ClientError::Timeout))));
+    client.put(1, b"a", b"1").unwrap();
+    assert_eq!(subscription.next().unwrap().unwrap(), event(EventKind::Put, 1, b"a"));
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{frame, Hangup, ScriptedServer, Step};
+use scalerize_client::protocol::OP_GET;
+use scalerize_client::{ClientError, ClientOptions, ScalerizeClient};
+
+fn connect(server: &ScriptedServer, timeout: Duration) -> ScalerizeClient {
+    ScalerizeClient::connect_with(server.path(), ClientOptions::new().read_timeout(Some(timeout))).unwrap()
+}
+
+#[test]
+fn silent_server_times_out_within_the_read_timeout() {
+    // Accepts and reads, never answers.
+    let server = ScriptedServer::start(vec![]);
+    let mut client = connect(&server, Duration::from_millis(50));
+
+    let started = Instant::now();
+    let result = client.get(1, b"a");
+    let elapsed = started.elapsed();
+
+    assert!(matches!(result, Err(ClientError::Timeout)), "{:?}", result);
+    assert!(elapsed >= Duration::from_millis(50), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(1), "{:?}", elapsed);
+}
+
+#[test]
+fn late_answer_is_not_read_as_the_next_reply() {
+    let server = ScriptedServer::start(vec![
+        Step::ReplyAfter(Duration::from_millis(200), frame(1, b"value of a")),
+        Step::Reply(frame(1, b"value of b")),
+    ]);
+    let mut client = connect(&server, Duration::from_millis(50));
+
+    assert!(matches!(client.get(1, b"a"), Err(ClientError::Timeout)));
+    std::thread::sleep(Duration::from_millis(300));
+
+    let second = client.get(1, b"b");
+    assert!(second.as_ref().is_err_and(ClientError::is_disconnect), "{:?}", second);
+    drop(client);
+
+    // Only the first request made it out before the stream was shut down.
+    let transcript = server.finish();
+    assert_eq!(transcript.bytes(), [&[OP_GET, 1][..], b"a"].concat());
+    assert_ne!(transcript.hangup, Hangup::TimedOut);
+}
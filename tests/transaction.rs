@@ -0,0 +1,100 @@
+mod common;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::protocol::{OP_COMMIT, OP_DELETE, OP_PUT, STATUS_ERROR, STATUS_SUCCESS, STATUS_UNSUPPORTED};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ScalerizeClient};
+
+#[test]
+fn put_and_delete_encode_to_one_commit_frame() {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(STATUS_SUCCESS, b""))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    let mut txn = client.begin(2);
+    txn.put(b"a", b"1").delete(b"b");
+    assert_eq!(txn.len(), 2);
+    txn.commit().unwrap();
+    drop(client);
+
+    #[rustfmt::skip]
+    let expected = [
+        OP_COMMIT, 2, 0, 0, 0, 2,
+        OP_PUT, 0, 0, 0, 1, b'a', 0, 0, 0, 1, b'1',
+        OP_DELETE, 0, 0, 0, 1, b'b',
+    ];
+    assert_eq!(server.finish().bytes(), expected);
+}
+
+#[test]
+fn empty_commit_and_rollback_send_nothing() {
+    let server = ScriptedServer::start(vec![]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    client.begin(1).commit().unwrap();
+    let mut txn = client.begin(1);
+    txn.put(b"a", b"1");
+    txn.rollback();
+    drop(client);
+
+    assert!(server.finish().reads.is_empty());
+}
+
+#[test]
+fn rejected_commit_names_the_failing_op() {
+    let mut payload = 1u32.to_be_bytes().to_vec();
+    payload.extend_from_slice(b"key is locked");
+    let server = ScriptedServer::start(vec![
+        Step::Reply(frame(STATUS_ERROR, &payload)),
+        Step::Reply(frame(STATUS_ERROR, b"no")),
+        Step::Reply(frame(STATUS_UNSUPPORTED, b"")),
+    ]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    let mut txn = client.begin(1);
+    txn.put(b"a", b"1").delete(b"b");
+    match txn.commit() {
+        Err(ClientError::OperationFailed(message)) => assert_eq!(message, b"transaction op 1: key is locked"),
+        other => panic!("expected OperationFailed, got {:?}", other),
+    }
+
+    // Too short to carry an index: passed through as is.
+    let mut txn = client.begin(1);
+    txn.delete(b"b");
+    match txn.commit() {
+        Err(ClientError::OperationFailed(message)) => assert_eq!(message, b"no"),
+        other => panic!("expected OperationFailed, got {:?}", other),
+    }
+
+    let mut txn = client.begin(1);
+    txn.delete(b"b");
+    assert!(matches!(txn.commit(), Err(ClientError::Unsupported("commit"))));
+}
+
+#[test]
+fn invalid_op_is_rejected_before_sending() {
+    let server = ScriptedServer::start(vec![]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    let mut txn = client.begin(1);
+    txn.put(b"a", b"1").delete(b"");
+    match txn.commit() {
+        Err(ClientError::InvalidArgument(message)) => assert_eq!(message, "op 1: key is empty"),
+        other => panic!("expected InvalidArgument, got {:?}", other),
+    }
+    drop(client);
+    assert!(server.finish().reads.is_empty());
+}
+
+#[test]
+fn mock_applies_the_whole_transaction() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+    client.put(1, b"old", b"x").unwrap();
+
+    let mut txn = client.begin(1);
+    txn.put(b"new", b"1").delete(b"old");
+    txn.commit().unwrap();
+
+    assert_eq!(server.value(1, b"new"), Some(b"1".to_vec()));
+    assert_eq!(server.value(1, b"old"), None);
+}
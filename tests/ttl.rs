@@ -0,0 +1,85 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{frame, ScriptedServer, Step};
+use scalerize_client::protocol::{OP_PUT_TTL, STATUS_ERROR, STATUS_NOT_FOUND, STATUS_SUCCESS, STATUS_UNSUPPORTED};
+use scalerize_client::testing::MockServer;
+use scalerize_client::{ClientError, ScalerizeClient};
+
+fn put_ttl_request(ttl_secs: u64) -> Vec<u8> {
+    let mut expected = vec![OP_PUT_TTL, 1, 0, 0, 0, 3, b'k', b'e', b'y', 0, 0, 0, 1, b'v'];
+    expected.extend_from_slice(&ttl_secs.to_be_bytes());
+    expected
+}
+
+fn sent_for(ttl: Duration) -> Vec<u8> {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(STATUS_SUCCESS, b""))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+    client.put_with_ttl(1, b"key", b"v", ttl).unwrap();
+    drop(client);
+    server.finish().bytes()
+}
+
+#[test]
+fn ttl_is_sent_in_whole_seconds_rounded_up() {
+    assert_eq!(sent_for(Duration::from_secs(30)), put_ttl_request(30));
+    assert_eq!(sent_for(Duration::from_millis(1500)), put_ttl_request(2));
+    assert_eq!(sent_for(Duration::from_nanos(1)), put_ttl_request(1));
+}
+
+#[test]
+fn longest_ttl_saturates_instead_of_overflowing() {
+    assert_eq!(sent_for(Duration::MAX), put_ttl_request(u64::MAX));
+}
+
+#[test]
+fn zero_ttl_is_rejected_before_anything_is_sent() {
+    let server = ScriptedServer::start(vec![]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    assert!(matches!(
+        client.put_with_ttl(1, b"key", b"v", Duration::ZERO),
+        Err(ClientError::InvalidArgument(_))
+    ));
+    drop(client);
+    assert!(server.finish().reads.is_empty());
+}
+
+#[test]
+fn servers_without_ttl_support_are_unsupported() {
+    for status in [STATUS_UNSUPPORTED, STATUS_NOT_FOUND, 0x7f] {
+        let server = ScriptedServer::start(vec![Step::Reply(frame(status, b""))]);
+        let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+        assert!(
+            matches!(
+                client.put_with_ttl(1, b"key", b"v", Duration::from_secs(1)),
+                Err(ClientError::Unsupported("put_with_ttl"))
+            ),
+            "status {}",
+            status
+        );
+    }
+}
+
+#[test]
+fn server_error_stays_operation_failed() {
+    let server = ScriptedServer::start(vec![Step::Reply(frame(STATUS_ERROR, b"disk full"))]);
+    let mut client = ScalerizeClient::connect_to(server.path()).unwrap();
+
+    match client.put_with_ttl(1, b"key", b"v", Duration::from_secs(1)) {
+        Err(ClientError::OperationFailed(message)) => assert_eq!(message, b"disk full"),
+        other => panic!("expected OperationFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn value_with_a_ttl_is_readable_until_it_expires() {
+    let server = MockServer::start().unwrap();
+    let mut client = server.connect().unwrap();
+
+    client.put_with_ttl(1, b"key", b"v", Duration::from_secs(3600)).unwrap();
+    assert_eq!(client.get(1, b"key").unwrap(), Some(b"v".to_vec()));
+    client.put_with_ttl(1, b"forever", b"v", Duration::MAX).unwrap();
+    assert_eq!(client.get(1, b"forever").unwrap(), Some(b"v".to_vec()));
+}